@@ -0,0 +1,169 @@
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        egl::{EGLContext, EGLDevice, EGLDisplay},
+        renderer::{
+            damage::OutputDamageTracker,
+            gles::{GlesRenderbuffer, GlesRenderer, GlesTexture},
+            Bind, Offscreen,
+        },
+    },
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::calloop::EventLoop,
+    utils::{Buffer, Size, Transform},
+};
+
+use crate::{
+    config::Config,
+    render::{pointer::PointerElement, render_output},
+    state::{NoWayData, NoWayState},
+};
+
+use super::{initialize_frame_loop, set_output_refresh, Backend, CursorState, RendererKind};
+
+const HEADLESS_OUTPUT_SIZE: (i32, i32) = (1920, 1080);
+
+pub struct HeadlessBackend {
+    renderer: GlesRenderer,
+    outputs: Vec<HeadlessOutput>,
+    pointer_element: PointerElement<GlesTexture>,
+}
+
+pub struct HeadlessOutput {
+    output: Output,
+    buffer: GlesRenderbuffer,
+    damage_tracker: OutputDamageTracker,
+    age: usize,
+}
+
+pub fn initialize_headless(
+    event_loop: &mut EventLoop<NoWayData>,
+    data: &mut NoWayData,
+    renderer: RendererKind,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let display = &mut data.display;
+    let state = &mut data.state;
+
+    // The software renderer picks Mesa's CPU rasterizer even if a GPU is present.
+    let software = renderer == RendererKind::Software;
+    let device = EGLDevice::enumerate()?
+        .find(|device| !software || is_software_device(device))
+        .ok_or(if software {
+            "no software EGL device (Mesa's llvmpipe) available for headless rendering"
+        } else {
+            "no EGL device available for headless rendering"
+        })?;
+    tracing::info!("Using EGL device {:?} for headless rendering", device);
+
+    let egl_display = EGLDisplay::new(device)?;
+    let context = EGLContext::new(&egl_display)?;
+    let mut renderer = unsafe { GlesRenderer::new(context)? };
+
+    let output_count = std::env::var("NOWAY_HEADLESS_OUTPUTS")
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let mode = Mode {
+        size: HEADLESS_OUTPUT_SIZE.into(),
+        refresh: state.config.output.refresh,
+    };
+
+    let mut outputs = Vec::with_capacity(output_count);
+    for index in 0..output_count {
+        let output = Output::new(
+            format!("headless-{}", index),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Headless".into(),
+            },
+        );
+        let _global = output.create_global::<NoWayState>(&display.handle());
+
+        let location = (index as i32 * mode.size.w, 0).into();
+        output.change_current_state(Some(mode), Some(Transform::Normal), None, Some(location));
+        output.set_preferred(mode);
+        state.space.map_output(&output, location);
+
+        let buffer_size = Size::<i32, Buffer>::from((mode.size.w, mode.size.h));
+        let buffer: GlesRenderbuffer = renderer.create_buffer(Fourcc::Abgr8888, buffer_size)?;
+        let damage_tracker = OutputDamageTracker::from_output(&output);
+
+        outputs.push(HeadlessOutput {
+            output,
+            buffer,
+            damage_tracker,
+            age: 0,
+        });
+    }
+
+    let backend = HeadlessBackend {
+        renderer,
+        outputs,
+        pointer_element: PointerElement::default(),
+    };
+
+    initialize_frame_loop(event_loop, data, backend)
+}
+
+fn is_software_device(device: &EGLDevice) -> bool {
+    device
+        .extensions()
+        .iter()
+        .any(|extension| extension == "EGL_MESA_device_software")
+}
+
+impl Backend for HeadlessBackend {
+    fn outputs(&self) -> Vec<Output> {
+        self.outputs
+            .iter()
+            .map(|headless_output| headless_output.output.clone())
+            .collect()
+    }
+
+    fn render_output(
+        &mut self,
+        output: &Output,
+        state: &NoWayState,
+        cursor: &CursorState,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(headless_output) = self
+            .outputs
+            .iter_mut()
+            .find(|headless_output| &headless_output.output == output)
+        else {
+            return Ok(false);
+        };
+
+        self.renderer.bind(headless_output.buffer.clone())?;
+
+        let custom_elements = cursor.render_elements(
+            &mut self.pointer_element,
+            &mut self.renderer,
+            output,
+            &state.space,
+        );
+
+        render_output(
+            output,
+            state,
+            custom_elements,
+            &mut self.renderer,
+            &mut headless_output.damage_tracker,
+            headless_output.age,
+        )?;
+        // The same offscreen buffer is reused every frame, so it always holds the previous one.
+        headless_output.age = 1;
+
+        Ok(true)
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        for headless_output in &self.outputs {
+            set_output_refresh(&headless_output.output, config.output.refresh);
+        }
+    }
+}
@@ -1 +1,283 @@
+use std::{sync::Mutex, time::Duration};
+
+use smithay::{
+    backend::renderer::{element::AsRenderElements, ImportAll, ImportMem, Renderer},
+    desktop::{layer_map_for_output, Space},
+    input::pointer::{CursorImageAttributes, CursorImageStatus},
+    output::{Mode, Output},
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        EventLoop,
+    },
+    utils::{IsAlive, Logical, Point, Scale},
+    wayland::compositor,
+};
+
+use crate::{
+    config::Config,
+    handlers::pointer_constraints::PointerConstraintKind,
+    render::{custom::CustomRenderElements, pointer::PointerElement, window::WindowElement},
+    state::{NoWayData, NoWayState},
+};
+
+pub mod headless;
+pub mod udev;
 pub mod winit;
+pub mod x11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Winit,
+    X11,
+    Udev,
+    Headless,
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "winit" => Ok(Self::Winit),
+            "x11" => Ok(Self::X11),
+            "udev" => Ok(Self::Udev),
+            "headless" => Ok(Self::Headless),
+            _ => Err(()),
+        }
+    }
+}
+
+impl BackendKind {
+    pub fn initialize(
+        self,
+        event_loop: &mut EventLoop<NoWayData>,
+        data: &mut NoWayData,
+        renderer: RendererKind,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Self::Winit => winit::initialize_winit(event_loop, data, renderer),
+            Self::X11 => x11::initialize_x11(event_loop, data, renderer),
+            Self::Udev => udev::initialize_udev(event_loop, data, renderer),
+            Self::Headless => headless::initialize_headless(event_loop, data, renderer),
+        }
+    }
+}
+
+/// How frames are rendered. Both go through EGL and GLES, the smithay version NoWay uses has no
+/// pixman renderer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RendererKind {
+    /// The driver of the GPU the backend renders on.
+    #[default]
+    Gles,
+    /// Mesa's CPU rasterizer (llvmpipe), for machines without GPU drivers.
+    Software,
+}
+
+impl std::str::FromStr for RendererKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gles" => Ok(Self::Gles),
+            "software" => Ok(Self::Software),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Runs `init` with Mesa forced onto its software rasterizer if the software renderer was
+/// chosen. Mesa picks the driver when an EGL display is initialized, so the variable is only set
+/// meanwhile, and spawned clients do not inherit it.
+pub fn with_renderer<T>(renderer: RendererKind, init: impl FnOnce() -> T) -> T {
+    const VARIABLE: &str = "LIBGL_ALWAYS_SOFTWARE";
+    if renderer != RendererKind::Software || std::env::var_os(VARIABLE).is_some() {
+        return init();
+    }
+
+    std::env::set_var(VARIABLE, "1");
+    let result = init();
+    std::env::remove_var(VARIABLE);
+    result
+}
+
+/// Cursor image and hotspot-adjusted location for the frame being rendered.
+#[derive(Debug, Clone)]
+pub struct CursorState {
+    pub status: CursorImageStatus,
+    pub location: Point<f64, Logical>,
+}
+
+impl CursorState {
+    pub fn from_state(state: &NoWayState) -> Self {
+        let mut cursor_guard = state.cursor_status.lock().unwrap();
+        if let CursorImageStatus::Surface(surface) = cursor_guard.clone() {
+            if !surface.alive() {
+                *cursor_guard = CursorImageStatus::Default;
+            }
+        }
+
+        let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = *cursor_guard {
+            compositor::with_states(surface, |states| {
+                states
+                    .data_map
+                    .get::<Mutex<CursorImageAttributes>>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .hotspot
+            })
+        } else {
+            (0, 0).into()
+        };
+
+        Self {
+            status: cursor_guard.clone(),
+            location: state.pointer_location - cursor_hotspot.to_f64(),
+        }
+    }
+
+    /// Whether the backend should draw its own cursor instead of a client-provided surface.
+    pub fn backend_cursor_visible(&self) -> bool {
+        !matches!(self.status, CursorImageStatus::Surface(_))
+    }
+
+    pub fn render_elements<R>(
+        &self,
+        pointer_element: &mut PointerElement<R::TextureId>,
+        renderer: &mut R,
+        output: &Output,
+        space: &Space<WindowElement>,
+    ) -> Vec<CustomRenderElements<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        R::TextureId: Clone + 'static,
+    {
+        let Some(output_geo) = space.output_geometry(output) else {
+            return vec![];
+        };
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let location = (self.location - output_geo.loc.to_f64())
+            .to_physical(scale)
+            .to_i32_round();
+
+        pointer_element.set_status(self.status.clone());
+        pointer_element.render_elements(renderer, location, scale)
+    }
+}
+
+/// A source of outputs that NoWay can render into.
+///
+/// Backends own their outputs, renderer and the way frames are submitted; everything that does
+/// not depend on the backend (cursor, frame callbacks, space maintenance) lives in
+/// [`render_frame`].
+pub trait Backend {
+    /// Dispatches pending backend events. Returns `false` once the backend has shut down.
+    fn dispatch(&mut self, _state: &mut NoWayState) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(true)
+    }
+
+    fn outputs(&self) -> Vec<Output>;
+
+    /// Renders and submits a frame for the given output. Returns whether a frame was submitted.
+    fn render_output(
+        &mut self,
+        output: &Output,
+        state: &NoWayState,
+        cursor: &CursorState,
+    ) -> Result<bool, Box<dyn std::error::Error>>;
+
+    fn set_cursor_visible(&mut self, _visible: bool) {}
+
+    /// Keeps the host cursor in place or inside the window while a client constrains the pointer.
+    fn set_cursor_grab(&mut self, _constraint: Option<PointerConstraintKind>) {}
+
+    /// Applies settings of a reloaded config to the outputs owned by this backend.
+    fn apply_config(&mut self, _config: &Config) {}
+}
+
+/// Changes the refresh rate of a virtual output, keeping its current size.
+pub fn set_output_refresh(output: &Output, refresh: i32) {
+    if let Some(mode) = output.current_mode() {
+        let mode = Mode { refresh, ..mode };
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+    }
+}
+
+pub fn initialize_frame_loop<B: Backend + 'static>(
+    event_loop: &mut EventLoop<NoWayData>,
+    data: &mut NoWayData,
+    mut backend: B,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("WAYLAND_DISPLAY", &data.state.socket_name);
+
+    let timer = Timer::immediate();
+    event_loop
+        .handle()
+        .insert_source(timer, move |_, _, data| {
+            render_frame(&mut backend, data).unwrap();
+            TimeoutAction::ToDuration(Duration::from_millis(16))
+        })?;
+
+    Ok(())
+}
+
+pub fn render_frame<B: Backend>(
+    backend: &mut B,
+    data: &mut NoWayData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let display = &mut data.display;
+    let state = &mut data.state;
+
+    if !backend.dispatch(state)? {
+        state.loop_signal.stop();
+        return Ok(());
+    }
+
+    if state.config_changed {
+        backend.apply_config(&state.config);
+        state.config_changed = false;
+    }
+
+    state.animate_strips();
+
+    let cursor = CursorState::from_state(state);
+    for output in backend.outputs() {
+        // Keeps layer surfaces in place when the output mode changed.
+        layer_map_for_output(&output).arrange();
+
+        if !backend.render_output(&output, state, &cursor)? {
+            continue;
+        }
+
+        state.space.elements_for_output(&output).for_each(|window| {
+            window.send_frame(
+                &output,
+                state.start_time.elapsed(),
+                Some(Duration::ZERO),
+                |_, _| Some(output.clone()),
+            )
+        });
+
+        let mut layer_map = layer_map_for_output(&output);
+        for layer in layer_map.layers() {
+            layer.send_frame(
+                &output,
+                state.start_time.elapsed(),
+                Some(Duration::ZERO),
+                |_, _| Some(output.clone()),
+            );
+        }
+        layer_map.cleanup();
+    }
+
+    backend.set_cursor_visible(cursor.backend_cursor_visible());
+    backend.set_cursor_grab(state.active_pointer_constraint());
+    state.space.refresh();
+    state.popups.cleanup();
+    display.flush_clients()?;
+
+    Ok(())
+}
@@ -0,0 +1,494 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    os::fd::FromRawFd,
+    path::Path,
+    rc::Rc,
+};
+
+use smithay::{
+    backend::{
+        allocator::{
+            gbm::{GbmAllocator, GbmBufferFlags, GbmDevice},
+            Fourcc,
+        },
+        drm::{compositor::DrmCompositor, DrmDevice, DrmDeviceFd, DrmEvent, DrmNode},
+        egl::{EGLContext, EGLDisplay},
+        libinput::{LibinputInputBackend, LibinputSessionInterface},
+        renderer::gles::{GlesRenderer, GlesTexture},
+        session::{libseat::LibSeatSession, Event as SessionEvent, Session},
+        udev::{self, UdevEvent},
+    },
+    desktop::Space,
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::{
+        calloop::{EventLoop, RegistrationToken},
+        drm::control::{connector, crtc, Device as ControlDevice, ModeTypeFlags},
+        input::Libinput,
+        nix::fcntl::OFlag,
+        wayland_server::backend::GlobalId,
+    },
+    utils::{DeviceFd, Logical, Point},
+};
+
+use crate::{
+    render::{
+        output_elements,
+        pointer::{default_cursor_texture, PointerElement},
+        window::WindowElement,
+    },
+    state::{NoWayData, NoWayState},
+};
+
+use super::{initialize_frame_loop, with_renderer, Backend, CursorState, RendererKind};
+
+const SUPPORTED_FORMATS: &[Fourcc] = &[
+    Fourcc::Abgr2101010,
+    Fourcc::Argb2101010,
+    Fourcc::Abgr8888,
+    Fourcc::Argb8888,
+];
+
+type GbmDrmCompositor =
+    DrmCompositor<GbmAllocator<DrmDeviceFd>, GbmDevice<DrmDeviceFd>, (), DrmDeviceFd>;
+
+struct UdevOutput {
+    output: Output,
+    global: GlobalId,
+    compositor: GbmDrmCompositor,
+    pending_frame: bool,
+}
+
+struct UdevDevice {
+    drm: DrmDevice,
+    gbm: GbmDevice<DrmDeviceFd>,
+    renderer: GlesRenderer,
+    pointer_element: PointerElement<GlesTexture>,
+    outputs: HashMap<crtc::Handle, UdevOutput>,
+    registration_token: RegistrationToken,
+}
+
+struct UdevInner {
+    session: LibSeatSession,
+    renderer: RendererKind,
+    devices: HashMap<DrmNode, UdevDevice>,
+}
+
+pub struct UdevBackend {
+    inner: Rc<RefCell<UdevInner>>,
+}
+
+pub fn initialize_udev(
+    event_loop: &mut EventLoop<NoWayData>,
+    data: &mut NoWayData,
+    renderer: RendererKind,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (session, session_notifier) = LibSeatSession::new()?;
+    let seat_name = session.seat();
+    tracing::info!("Opened session on seat {}", seat_name);
+
+    data.state.session = Some(session.clone());
+    let inner = Rc::new(RefCell::new(UdevInner {
+        session: session.clone(),
+        renderer,
+        devices: HashMap::new(),
+    }));
+
+    let udev_backend = udev::UdevBackend::new(&seat_name)?;
+    for (device_id, path) in udev_backend.device_list() {
+        let added = DrmNode::from_dev_id(device_id)
+            .map_err(Into::into)
+            .and_then(|node| device_added(&inner, &mut data.state, node, path));
+        if let Err(err) = added {
+            tracing::warn!("Skipping DRM device {}: {}", path.display(), err);
+        }
+    }
+
+    let udev_inner = inner.clone();
+    event_loop
+        .handle()
+        .insert_source(udev_backend, move |event, _, data| match event {
+            UdevEvent::Added { device_id, path } => {
+                let added = DrmNode::from_dev_id(device_id)
+                    .map_err(Into::into)
+                    .and_then(|node| device_added(&udev_inner, &mut data.state, node, &path));
+                if let Err(err) = added {
+                    tracing::warn!("Skipping DRM device {}: {}", path.display(), err);
+                }
+            }
+            UdevEvent::Changed { device_id } => {
+                if let Ok(node) = DrmNode::from_dev_id(device_id) {
+                    device_changed(&udev_inner, &mut data.state, node);
+                }
+            }
+            UdevEvent::Removed { device_id } => {
+                if let Ok(node) = DrmNode::from_dev_id(device_id) {
+                    device_removed(&udev_inner, &mut data.state, node);
+                }
+            }
+        })?;
+
+    let mut libinput_context =
+        Libinput::new_with_udev::<LibinputSessionInterface<LibSeatSession>>(session.clone().into());
+    libinput_context
+        .udev_assign_seat(&seat_name)
+        .map_err(|_| "failed to assign libinput to seat")?;
+    let libinput_backend = LibinputInputBackend::new(libinput_context.clone());
+    event_loop
+        .handle()
+        .insert_source(libinput_backend, move |event, _, data| {
+            data.state.process_input_event(event)
+        })?;
+
+    let session_inner = inner.clone();
+    event_loop
+        .handle()
+        .insert_source(session_notifier, move |event, _, _| match event {
+            SessionEvent::PauseSession => {
+                tracing::info!("Pausing session");
+                libinput_context.suspend();
+                for device in session_inner.borrow_mut().devices.values_mut() {
+                    device.drm.pause();
+                }
+            }
+            SessionEvent::ActivateSession => {
+                tracing::info!("Resuming session");
+                if libinput_context.resume().is_err() {
+                    tracing::warn!("Failed to resume libinput context");
+                }
+                for device in session_inner.borrow_mut().devices.values_mut() {
+                    device.drm.activate();
+                    for udev_output in device.outputs.values_mut() {
+                        // The primary plane has no content after a VT switch, force a full redraw.
+                        udev_output.compositor.reset_buffers();
+                        udev_output.pending_frame = false;
+                    }
+                }
+            }
+        })?;
+
+    initialize_frame_loop(event_loop, data, UdevBackend { inner })
+}
+
+impl NoWayState {
+    pub fn switch_vt(&mut self, vt: i32) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Err(err) = session.change_vt(vt) {
+            tracing::warn!("Failed to switch to VT {}: {:?}", vt, err);
+        }
+    }
+}
+
+fn device_added(
+    inner: &Rc<RefCell<UdevInner>>,
+    state: &mut NoWayState,
+    node: DrmNode,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut udev = inner.borrow_mut();
+
+    let fd = udev.session.open(
+        path,
+        OFlag::O_RDWR | OFlag::O_CLOEXEC | OFlag::O_NOCTTY | OFlag::O_NONBLOCK,
+    )?;
+    let fd = DrmDeviceFd::new(unsafe { DeviceFd::from_raw_fd(fd) });
+
+    let (drm, drm_notifier) = DrmDevice::new(fd.clone(), true)?;
+    let gbm = GbmDevice::new(fd)?;
+
+    // With the software renderer, Mesa renders into dumb buffers of the DRM device.
+    let egl_display = with_renderer(udev.renderer, || EGLDisplay::new(gbm.clone()))?;
+    let context = EGLContext::new(&egl_display)?;
+    let mut renderer = unsafe { GlesRenderer::new(context)? };
+
+    let mut pointer_element = PointerElement::default();
+    pointer_element.set_texture(default_cursor_texture(&mut renderer)?);
+
+    let drm_inner = inner.clone();
+    let registration_token =
+        state
+            .loop_handle
+            .insert_source(drm_notifier, move |event, _, _| match event {
+                DrmEvent::VBlank(crtc) => {
+                    let mut udev = drm_inner.borrow_mut();
+                    let Some(udev_output) = udev
+                        .devices
+                        .get_mut(&node)
+                        .and_then(|device| device.outputs.get_mut(&crtc))
+                    else {
+                        return;
+                    };
+
+                    if let Err(err) = udev_output.compositor.frame_submitted() {
+                        tracing::warn!("Failed to submit frame: {}", err);
+                    }
+                    udev_output.pending_frame = false;
+                }
+                DrmEvent::Error(err) => tracing::error!("DRM error on {:?}: {}", node, err),
+            })?;
+
+    tracing::info!("Added DRM device {:?} ({})", node, path.display());
+
+    udev.devices.insert(
+        node,
+        UdevDevice {
+            drm,
+            gbm,
+            renderer,
+            pointer_element,
+            outputs: HashMap::new(),
+            registration_token,
+        },
+    );
+    drop(udev);
+
+    device_changed(inner, state, node);
+    Ok(())
+}
+
+/// Rescans the connectors of a device, creating outputs for newly connected ones and removing
+/// outputs whose connector went away.
+fn device_changed(inner: &Rc<RefCell<UdevInner>>, state: &mut NoWayState, node: DrmNode) {
+    let mut udev = inner.borrow_mut();
+    let Some(device) = udev.devices.get_mut(&node) else {
+        return;
+    };
+
+    let res_handles = match device.drm.resource_handles() {
+        Ok(res_handles) => res_handles,
+        Err(err) => {
+            tracing::warn!("Failed to query resources of {:?}: {}", node, err);
+            return;
+        }
+    };
+
+    let connectors = res_handles
+        .connectors()
+        .iter()
+        .filter_map(|conn| device.drm.get_connector(*conn, true).ok())
+        .collect::<Vec<_>>();
+
+    // Drop outputs which are no longer driven by a connected connector.
+    let connected = connectors
+        .iter()
+        .filter(|info| info.state() == connector::State::Connected)
+        .map(|info| info.handle())
+        .collect::<Vec<_>>();
+    device.outputs.retain(|_, udev_output| {
+        let keep = udev_output
+            .compositor
+            .surface()
+            .current_connectors()
+            .iter()
+            .any(|conn| connected.contains(conn));
+        if !keep {
+            tracing::info!("Removing output {}", udev_output.output.name());
+            state.remove_output(&udev_output.output);
+            state
+                .display_handle
+                .remove_global::<NoWayState>(udev_output.global.clone());
+        }
+        keep
+    });
+
+    for connector_info in connectors
+        .into_iter()
+        .filter(|info| info.state() == connector::State::Connected)
+    {
+        let already_driven = device.outputs.values().any(|udev_output| {
+            udev_output
+                .compositor
+                .surface()
+                .current_connectors()
+                .contains(&connector_info.handle())
+        });
+        if already_driven {
+            continue;
+        }
+
+        let crtcs = connector_info
+            .encoders()
+            .iter()
+            .filter_map(|encoder| device.drm.get_encoder(*encoder).ok())
+            .flat_map(|encoder_info| res_handles.filter_crtcs(encoder_info.possible_crtcs()))
+            .collect::<Vec<_>>();
+
+        for crtc in crtcs {
+            // Skip CRTCs already used by previous connectors.
+            let Entry::Vacant(entry) = device.outputs.entry(crtc) else {
+                continue;
+            };
+
+            match connector_connected(
+                &mut device.drm,
+                &device.gbm,
+                &device.renderer,
+                state,
+                &connector_info,
+                crtc,
+            ) {
+                Ok(udev_output) => {
+                    entry.insert(udev_output);
+                }
+                Err(err) => tracing::warn!("Failed to initialize connector: {}", err),
+            }
+            break;
+        }
+    }
+}
+
+fn device_removed(inner: &Rc<RefCell<UdevInner>>, state: &mut NoWayState, node: DrmNode) {
+    let Some(device) = inner.borrow_mut().devices.remove(&node) else {
+        return;
+    };
+
+    for udev_output in device.outputs.values() {
+        state.remove_output(&udev_output.output);
+        state
+            .display_handle
+            .remove_global::<NoWayState>(udev_output.global.clone());
+    }
+
+    state.loop_handle.remove(device.registration_token);
+    tracing::info!("Removed DRM device {:?}", node);
+}
+
+fn connector_connected(
+    drm: &mut DrmDevice,
+    gbm: &GbmDevice<DrmDeviceFd>,
+    renderer: &GlesRenderer,
+    state: &mut NoWayState,
+    connector_info: &connector::Info,
+    crtc: crtc::Handle,
+) -> Result<UdevOutput, Box<dyn std::error::Error>> {
+    let drm_mode = connector_info
+        .modes()
+        .iter()
+        .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+        .or_else(|| connector_info.modes().first())
+        .copied()
+        .ok_or("connector has no modes")?;
+
+    let output_name = format!(
+        "{}-{}",
+        connector_info.interface().as_str(),
+        connector_info.interface_id()
+    );
+    let (physical_width, physical_height) = connector_info.size().unwrap_or((0, 0));
+
+    let output = Output::new(
+        output_name,
+        PhysicalProperties {
+            size: (physical_width as i32, physical_height as i32).into(),
+            subpixel: Subpixel::Unknown,
+            make: "Smithay".into(),
+            model: "Generic DRM".into(),
+        },
+    );
+    let global = output.create_global::<NoWayState>(&state.display_handle);
+
+    let mode = Mode::from(drm_mode);
+    let location = next_output_location(&state.space);
+    output.change_current_state(Some(mode), None, None, Some(location));
+    output.set_preferred(mode);
+
+    let surface = drm.create_surface(crtc, drm_mode, &[connector_info.handle()])?;
+    let allocator = GbmAllocator::new(
+        gbm.clone(),
+        GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT,
+    );
+    let render_formats = renderer.egl_context().dmabuf_render_formats().clone();
+
+    let compositor = DrmCompositor::new(
+        &output,
+        surface,
+        None,
+        allocator,
+        gbm.clone(),
+        SUPPORTED_FORMATS,
+        render_formats,
+        drm.cursor_size(),
+        Some(gbm.clone()),
+    )?;
+
+    state.space.map_output(&output, location);
+    state.add_output(&output);
+    tracing::info!("Added output {} at {:?}", output.name(), location);
+
+    Ok(UdevOutput {
+        output,
+        global,
+        compositor,
+        pending_frame: false,
+    })
+}
+
+/// Places new outputs to the right of the existing ones.
+fn next_output_location(space: &Space<WindowElement>) -> Point<i32, Logical> {
+    let x = space
+        .outputs()
+        .filter_map(|output| space.output_geometry(output))
+        .map(|geometry| geometry.loc.x + geometry.size.w)
+        .max()
+        .unwrap_or(0);
+    (x, 0).into()
+}
+
+impl Backend for UdevBackend {
+    fn outputs(&self) -> Vec<Output> {
+        self.inner
+            .borrow()
+            .devices
+            .values()
+            .flat_map(|device| device.outputs.values())
+            .map(|udev_output| udev_output.output.clone())
+            .collect()
+    }
+
+    fn render_output(
+        &mut self,
+        output: &Output,
+        state: &NoWayState,
+        cursor: &CursorState,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut udev = self.inner.borrow_mut();
+        if !udev.session.is_active() {
+            return Ok(false);
+        }
+
+        for device in udev.devices.values_mut() {
+            let Some(udev_output) = device
+                .outputs
+                .values_mut()
+                .find(|udev_output| &udev_output.output == output)
+            else {
+                continue;
+            };
+
+            if udev_output.pending_frame {
+                return Ok(false);
+            }
+
+            let renderer = &mut device.renderer;
+            let custom_elements =
+                cursor.render_elements(&mut device.pointer_element, renderer, output, &state.space);
+            let elements = output_elements(output, state, custom_elements, renderer);
+
+            let result = udev_output.compositor.render_frame::<_, _, GlesTexture>(
+                renderer,
+                &elements,
+                state.config.output.background,
+            )?;
+            if !result.is_empty {
+                udev_output.compositor.queue_frame(())?;
+                udev_output.pending_frame = true;
+            }
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
@@ -1,41 +1,51 @@
-use std::{sync::Mutex, time::Duration};
-
 use smithay::{
     backend::{
         renderer::{
             damage::OutputDamageTracker,
-            element::AsRenderElements,
             gles::{GlesRenderer, GlesTexture},
         },
         winit::{self, WinitError, WinitEvent, WinitEventLoop, WinitGraphicsBackend},
     },
-    input::pointer::{CursorImageAttributes, CursorImageStatus},
+    desktop::layer_map_for_output,
     output::{Mode, Output, PhysicalProperties, Subpixel},
-    reexports::calloop::{
-        timer::{TimeoutAction, Timer},
-        EventLoop,
-    },
-    utils::{IsAlive, Scale, Transform},
-    wayland::compositor,
+    reexports::{calloop::EventLoop, winit::window::CursorGrabMode},
+    utils::Transform,
 };
 
 use crate::{
+    config::Config,
+    handlers::pointer_constraints::PointerConstraintKind,
     render::{pointer::PointerElement, render_output},
     state::{NoWayData, NoWayState},
 };
 
+use super::{
+    initialize_frame_loop, set_output_refresh, with_renderer, Backend, CursorState, RendererKind,
+};
+
+pub struct WinitBackend {
+    backend: WinitGraphicsBackend<GlesRenderer>,
+    winit: WinitEventLoop,
+    output: Output,
+    damage_tracker: OutputDamageTracker,
+    pointer_element: PointerElement<GlesTexture>,
+    full_redraw: u8,
+    cursor_grab: CursorGrabMode,
+}
+
 pub fn initialize_winit(
     event_loop: &mut EventLoop<NoWayData>,
     data: &mut NoWayData,
+    renderer: RendererKind,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let display = &mut data.display;
     let state = &mut data.state;
 
-    let (mut backend, mut winit) = winit::init()?;
+    let (backend, winit) = with_renderer(renderer, winit::init)?;
 
     let mode = Mode {
         size: backend.window_size().physical_size,
-        refresh: 60_000,
+        refresh: state.config.output.refresh,
     };
 
     let output = Output::new(
@@ -57,131 +67,116 @@ pub fn initialize_winit(
     output.set_preferred(mode);
     state.space.map_output(&output, (0, 0));
 
-    let mut damage_tracker = OutputDamageTracker::from_output(&output);
-    let mut pointer_element = PointerElement::default();
-    let mut full_redraw = 4u8;
-
-    std::env::set_var("WAYLAND_DISPLAY", &state.socket_name);
-
-    let timer = Timer::immediate();
-    event_loop
-        .handle()
-        .insert_source(timer, move |_, _, data| {
-            winit_dispatch(
-                &mut backend,
-                &mut winit,
-                data,
-                &output,
-                &mut damage_tracker,
-                &mut pointer_element,
-                &mut full_redraw,
-            )
-            .unwrap();
-            TimeoutAction::ToDuration(Duration::from_millis(16))
-        })?;
-
-    Ok(())
-}
-
-pub fn winit_dispatch(
-    backend: &mut WinitGraphicsBackend<GlesRenderer>,
-    winit: &mut WinitEventLoop,
-    data: &mut NoWayData,
-    output: &Output,
-    damage_tracker: &mut OutputDamageTracker,
-    pointer_element: &mut PointerElement<GlesTexture>,
-    full_redraw: &mut u8,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let display = &mut data.display;
-    let state = &mut data.state;
+    let damage_tracker = OutputDamageTracker::from_output(&output);
 
-    let res = winit.dispatch_new_events(|event| match event {
-        WinitEvent::Resized { size, .. } => {
-            output.change_current_state(
-                Some(Mode {
-                    size,
-                    refresh: 60_000,
-                }),
-                None,
-                None,
-                None,
-            );
-        }
-        WinitEvent::Input(event) => state.process_input_event(event),
-        _ => (),
-    });
-
-    if let Err(WinitError::WindowClosed) = res {
-        state.loop_signal.stop();
-        return Ok(());
-    } else {
-        res?;
-    }
+    let backend = WinitBackend {
+        backend,
+        winit,
+        output,
+        damage_tracker,
+        pointer_element: PointerElement::default(),
+        full_redraw: 4,
+        cursor_grab: CursorGrabMode::None,
+    };
 
-    *full_redraw = full_redraw.saturating_sub(1);
-    let scale = Scale::from(output.current_scale().fractional_scale());
+    initialize_frame_loop(event_loop, data, backend)
+}
 
-    let mut cursor_guard = state.cursor_status.lock().unwrap();
-    if let CursorImageStatus::Surface(surface) = cursor_guard.clone() {
-        if !surface.alive() {
-            *cursor_guard = CursorImageStatus::Default;
+impl Backend for WinitBackend {
+    fn dispatch(&mut self, state: &mut NoWayState) -> Result<bool, Box<dyn std::error::Error>> {
+        let output = &self.output;
+        let res = self.winit.dispatch_new_events(|event| match event {
+            WinitEvent::Resized { size, .. } => {
+                output.change_current_state(
+                    Some(Mode {
+                        size,
+                        refresh: state.config.output.refresh,
+                    }),
+                    None,
+                    None,
+                    None,
+                );
+                // Layer surfaces and windows follow the new size of the window.
+                layer_map_for_output(output).arrange();
+                state.arrange_output(output);
+            }
+            WinitEvent::Input(event) => state.process_input_event(event),
+            _ => (),
+        });
+
+        match res {
+            Err(WinitError::WindowClosed) => Ok(false),
+            res => res.map(|_| true).map_err(Into::into),
         }
     }
-    pointer_element.set_status(cursor_guard.clone());
-    let cursor_visible = !matches!(*cursor_guard, CursorImageStatus::Surface(_));
-
-    let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = *cursor_guard {
-        compositor::with_states(surface, |states| {
-            states
-                .data_map
-                .get::<Mutex<CursorImageAttributes>>()
-                .unwrap()
-                .lock()
-                .unwrap()
-                .hotspot
-        })
-    } else {
-        (0, 0).into()
-    };
-    let cursor_pos = state.pointer_location - cursor_hotspot.to_f64();
-    let cursor_pos_scaled = cursor_pos.to_physical(scale).to_i32_round();
-
-    backend.bind()?;
-    let age = if *full_redraw > 0 {
-        0
-    } else {
-        backend.buffer_age().unwrap_or(0)
-    };
 
-    let renderer = backend.renderer();
-    let mut custom_elements = Vec::new();
-    custom_elements.extend(pointer_element.render_elements(renderer, cursor_pos_scaled, scale));
+    fn outputs(&self) -> Vec<Output> {
+        vec![self.output.clone()]
+    }
 
-    let (damage, _) = render_output(
-        output,
-        &state.space,
-        custom_elements,
-        renderer,
-        damage_tracker,
-        age,
-    )?;
+    fn render_output(
+        &mut self,
+        output: &Output,
+        state: &NoWayState,
+        cursor: &CursorState,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.full_redraw = self.full_redraw.saturating_sub(1);
+
+        self.backend.bind()?;
+        let age = if self.full_redraw > 0 {
+            0
+        } else {
+            self.backend.buffer_age().unwrap_or(0)
+        };
+
+        let renderer = self.backend.renderer();
+        let custom_elements =
+            cursor.render_elements(&mut self.pointer_element, renderer, output, &state.space);
+
+        let (damage, _) = render_output(
+            output,
+            state,
+            custom_elements,
+            renderer,
+            &mut self.damage_tracker,
+            age,
+        )?;
+
+        if let Some(damage) = damage {
+            self.backend.submit(Some(&damage))?;
+        }
 
-    if let Some(damage) = damage {
-        backend.submit(Some(&damage))?;
+        Ok(true)
     }
 
-    state.space.elements().for_each(|window| {
-        window.send_frame(
-            output,
-            state.start_time.elapsed(),
-            Some(Duration::ZERO),
-            |_, _| Some(output.clone()),
-        )
-    });
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.backend.window().set_cursor_visible(visible);
+    }
 
-    backend.window().set_cursor_visible(cursor_visible);
-    state.space.refresh();
-    display.flush_clients()?;
+    fn set_cursor_grab(&mut self, constraint: Option<PointerConstraintKind>) {
+        let mode = match constraint {
+            Some(PointerConstraintKind::Lock) => CursorGrabMode::Locked,
+            Some(PointerConstraintKind::Confine) => CursorGrabMode::Confined,
+            None => CursorGrabMode::None,
+        };
+        if mode == self.cursor_grab {
+            return;
+        }
+        self.cursor_grab = mode;
+
+        // Not every platform has both modes, e.g. X11 cannot lock and macOS cannot confine.
+        let window = self.backend.window();
+        let result = window.set_cursor_grab(mode).or_else(|_| match mode {
+            CursorGrabMode::Locked => window.set_cursor_grab(CursorGrabMode::Confined),
+            CursorGrabMode::Confined => window.set_cursor_grab(CursorGrabMode::Locked),
+            CursorGrabMode::None => Ok(()),
+        });
+        if let Err(err) = result {
+            tracing::warn!("Failed to grab the cursor: {}", err);
+        }
+    }
 
-    Ok(())
+    fn apply_config(&mut self, config: &Config) {
+        set_output_refresh(&self.output, config.output.refresh);
+    }
 }
@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+
+use smithay::{
+    backend::{
+        allocator::{
+            dmabuf::DmabufAllocator,
+            gbm::{GbmAllocator, GbmBufferFlags, GbmDevice},
+        },
+        egl::{EGLContext, EGLDisplay},
+        input::InputEvent,
+        renderer::{
+            damage::OutputDamageTracker,
+            gles::{GlesRenderer, GlesTexture},
+            Bind,
+        },
+        x11::{Window, WindowBuilder, X11Backend as X11Source, X11Event, X11Input, X11Surface},
+    },
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::calloop::EventLoop,
+    utils::{DeviceFd, Transform},
+};
+
+use crate::{
+    config::Config,
+    render::{pointer::PointerElement, render_output},
+    state::{NoWayData, NoWayState},
+};
+
+use super::{initialize_frame_loop, set_output_refresh, Backend, CursorState, RendererKind};
+
+pub struct X11Output {
+    window: Window,
+    surface: X11Surface,
+    output: Output,
+    damage_tracker: OutputDamageTracker,
+}
+
+pub struct X11Backend {
+    renderer: GlesRenderer,
+    outputs: Vec<X11Output>,
+    pointer_element: PointerElement<GlesTexture>,
+}
+
+pub fn initialize_x11(
+    event_loop: &mut EventLoop<NoWayData>,
+    data: &mut NoWayData,
+    renderer: RendererKind,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Frames are presented in GPU buffers shared with the X server, which Mesa's software
+    // rasterizer cannot render into.
+    if renderer == RendererKind::Software {
+        return Err("the software renderer is not supported by the x11 backend".into());
+    }
+
+    let display = &mut data.display;
+    let state = &mut data.state;
+
+    let x11 = X11Source::new()?;
+    let handle = x11.handle();
+
+    // Render on the same device the X server uses for direct rendering. Frames are presented
+    // through DRI3 as well, so there is no fallback for X servers without it, such as Xvfb.
+    let (_node, fd) = handle.drm_node().map_err(|err| {
+        format!(
+            "the x11 backend needs an X server with DRI3 (e.g. Xorg or Xephyr -glamor, not \
+             Xvfb); without a GPU, use the headless backend: {}",
+            err
+        )
+    })?;
+    let gbm = GbmDevice::new(DeviceFd::from(fd))?;
+    let egl_display = EGLDisplay::new(gbm.clone())?;
+    let context = EGLContext::new(&egl_display)?;
+    let modifiers = context
+        .dmabuf_render_formats()
+        .iter()
+        .map(|format| format.modifier)
+        .collect::<HashSet<_>>();
+
+    let window_count = std::env::var("NOWAY_X11_OUTPUTS")
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let mut outputs = Vec::with_capacity(window_count);
+    let mut x = 0;
+    for index in 0..window_count {
+        let window = WindowBuilder::new()
+            .title(&format!("NoWay ({})", index))
+            .build(&handle)?;
+        let surface = handle.create_surface(
+            &window,
+            DmabufAllocator(GbmAllocator::new(gbm.clone(), GbmBufferFlags::RENDERING)),
+            modifiers.iter().cloned(),
+        )?;
+
+        let size = window.size();
+        let mode = Mode {
+            size: (size.w as i32, size.h as i32).into(),
+            refresh: state.config.output.refresh,
+        };
+
+        let output = Output::new(
+            format!("x11-{}", index),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "X11".into(),
+            },
+        );
+        let _global = output.create_global::<NoWayState>(&display.handle());
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            None,
+            Some((x, 0).into()),
+        );
+        output.set_preferred(mode);
+        state.space.map_output(&output, (x, 0));
+        x += mode.size.w;
+
+        let damage_tracker = OutputDamageTracker::from_output(&output);
+
+        outputs.push(X11Output {
+            window,
+            surface,
+            output,
+            damage_tracker,
+        });
+    }
+
+    let renderer = unsafe { GlesRenderer::new(context)? };
+
+    let windows = outputs
+        .iter()
+        .map(|x11_output| (x11_output.window.clone(), x11_output.output.clone()))
+        .collect::<Vec<_>>();
+    let output_list = outputs
+        .iter()
+        .map(|x11_output| x11_output.output.clone())
+        .collect::<Vec<_>>();
+    let output_for_window = move |window_id: u32| {
+        windows
+            .iter()
+            .find(|(window, _)| window.id() == window_id)
+            .map(|(_, output)| output.clone())
+    };
+
+    event_loop
+        .handle()
+        .insert_source(x11, move |event, _, data| match event {
+            X11Event::CloseRequested { .. } => data.state.loop_signal.stop(),
+            X11Event::Resized {
+                new_size,
+                window_id,
+            } => {
+                if let Some(output) = output_for_window(window_id) {
+                    let mode = Mode {
+                        size: (new_size.w as i32, new_size.h as i32).into(),
+                        refresh: data.state.config.output.refresh,
+                    };
+                    output.change_current_state(Some(mode), None, None, None);
+                    output.set_preferred(mode);
+                    layout_outputs(&mut data.state, &output_list);
+                }
+            }
+            X11Event::Input(InputEvent::PointerMotionAbsolute { event }) => {
+                // Absolute positions are relative to the X11 window the pointer is in.
+                let output = event
+                    .window()
+                    .and_then(|window| output_for_window(window.as_ref().id()));
+                if let Some(output) = output {
+                    data.state
+                        .pointer_motion_absolute::<X11Input>(&event, &output);
+                }
+            }
+            X11Event::Input(event) => data.state.process_input_event(event),
+            X11Event::Refresh { .. } | X11Event::PresentCompleted { .. } => {}
+        })?;
+
+    let backend = X11Backend {
+        renderer,
+        outputs,
+        pointer_element: PointerElement::default(),
+    };
+
+    initialize_frame_loop(event_loop, data, backend)
+}
+
+/// Places the outputs next to each other from left to right, so they do not overlap or leave
+/// gaps after one of the windows was resized.
+fn layout_outputs(state: &mut NoWayState, outputs: &[Output]) {
+    let mut x = 0;
+    for output in outputs {
+        state.space.map_output(output, (x, 0));
+        output.change_current_state(None, None, None, Some((x, 0).into()));
+        state.arrange_output(output);
+        x += output.current_mode().map_or(0, |mode| mode.size.w);
+    }
+}
+
+impl Backend for X11Backend {
+    fn outputs(&self) -> Vec<Output> {
+        self.outputs
+            .iter()
+            .map(|x11_output| x11_output.output.clone())
+            .collect()
+    }
+
+    fn render_output(
+        &mut self,
+        output: &Output,
+        state: &NoWayState,
+        cursor: &CursorState,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(x11_output) = self
+            .outputs
+            .iter_mut()
+            .find(|x11_output| &x11_output.output == output)
+        else {
+            return Ok(false);
+        };
+
+        let (buffer, age) = x11_output.surface.buffer()?;
+        self.renderer.bind(buffer)?;
+
+        let custom_elements = cursor.render_elements(
+            &mut self.pointer_element,
+            &mut self.renderer,
+            output,
+            &state.space,
+        );
+
+        let result = render_output(
+            output,
+            state,
+            custom_elements,
+            &mut self.renderer,
+            &mut x11_output.damage_tracker,
+            age.into(),
+        );
+        if let Err(err) = result {
+            x11_output.surface.reset_buffers();
+            return Err(err.into());
+        }
+
+        x11_output.surface.submit()?;
+        Ok(true)
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        for x11_output in &self.outputs {
+            x11_output.window.set_cursor_visible(visible);
+        }
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        for x11_output in &self.outputs {
+            set_output_refresh(&x11_output.output, config.output.refresh);
+        }
+    }
+}
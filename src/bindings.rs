@@ -0,0 +1,479 @@
+use std::str::FromStr;
+
+use smithay::{
+    desktop::space::SpaceElement,
+    input::keyboard::{keysyms, xkb, KeysymHandle, ModifiersState},
+    utils::{Logical, Point, Rectangle},
+};
+
+use crate::{
+    layout::{is_floating, LayoutKind},
+    render::window::WindowElement,
+    state::NoWayState,
+    workspace::WORKSPACE_COUNT,
+};
+
+/// Distance floating windows are moved or resized by with the keyboard.
+const KEYBOARD_STEP: i32 = 32;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub fn matches(&self, state: &ModifiersState) -> bool {
+        self.ctrl == state.ctrl
+            && self.alt == state.alt
+            && self.shift == state.shift
+            && self.logo == state.logo
+    }
+}
+
+/// Modifiers written as e.g. `Super+Shift`.
+impl FromStr for Modifiers {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Self::default();
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" | "mod1" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "super" | "logo" | "mod4" => modifiers.logo = true,
+                _ => return Err(()),
+            }
+        }
+
+        Ok(modifiers)
+    }
+}
+
+/// Modifiers and a key, written as e.g. `Super+Shift+Return`. Keys are xkb keysym names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub modifiers: Modifiers,
+    pub keysym: xkb::Keysym,
+}
+
+impl FromStr for KeyCombo {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = match s.rsplit_once('+') {
+            Some((modifiers, key)) => (modifiers.parse()?, key.trim()),
+            None => (Modifiers::default(), s.trim()),
+        };
+
+        // Keys are matched without the shift level applied, so `Super+Shift+Q` means the `q` key.
+        let keysym = xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE);
+        if keysym == keysyms::KEY_NoSymbol {
+            return Err(());
+        }
+
+        Ok(Self { modifiers, keysym })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FromStr for Direction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Compositor actions that can be bound to keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Runs a shell command.
+    Spawn(String),
+    /// Asks the focused window to close.
+    Close,
+    /// Focuses the nearest window in the direction.
+    Focus(Direction),
+    /// Moves a floating window, or swaps a tiled window with its neighbour in the direction.
+    Move(Direction),
+    /// Grows or shrinks a floating window towards the direction.
+    Resize(Direction),
+    SwitchWorkspace(usize),
+    MoveToWorkspace(usize),
+    SetLayout(LayoutKind),
+    ToggleFloating,
+    ToggleMaximize,
+    ToggleFullscreen,
+    Minimize,
+    Unminimize,
+    ConsumeIntoColumn,
+    ExpelFromColumn,
+    /// Switches to the next keyboard layout.
+    SwitchLayout,
+    /// Switches to another virtual terminal, only on the udev backend.
+    SwitchVt(i32),
+    Quit,
+    /// Reloads the config file.
+    Reload,
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, argument) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let argument = argument.trim();
+        let workspace = || {
+            argument
+                .parse()
+                .ok()
+                .filter(|workspace| (1..=WORKSPACE_COUNT).contains(workspace))
+                .ok_or(())
+        };
+
+        let action = match (name, argument) {
+            ("spawn", "") => return Err(()),
+            ("spawn", command) => Self::Spawn(command.to_string()),
+            ("close", "") => Self::Close,
+            ("focus", direction) => Self::Focus(direction.parse()?),
+            ("move", direction) => Self::Move(direction.parse()?),
+            ("resize", direction) => Self::Resize(direction.parse()?),
+            ("workspace", _) => Self::SwitchWorkspace(workspace()?),
+            ("move-to-workspace", _) => Self::MoveToWorkspace(workspace()?),
+            ("layout", layout) => Self::SetLayout(layout.parse()?),
+            ("toggle-floating", "") => Self::ToggleFloating,
+            ("toggle-maximize", "") => Self::ToggleMaximize,
+            ("toggle-fullscreen", "") => Self::ToggleFullscreen,
+            ("minimize", "") => Self::Minimize,
+            ("unminimize", "") => Self::Unminimize,
+            ("consume-into-column", "") => Self::ConsumeIntoColumn,
+            ("expel-from-column", "") => Self::ExpelFromColumn,
+            ("switch-layout", "") => Self::SwitchLayout,
+            ("switch-vt", vt) => Self::SwitchVt(vt.parse().map_err(|_| ())?),
+            ("quit", "") => Self::Quit,
+            ("reload", "") => Self::Reload,
+            _ => return Err(()),
+        };
+        Ok(action)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBinding {
+    pub combo: KeyCombo,
+    pub action: Action,
+}
+
+/// Virtual terminal xkb switches to with Ctrl+Alt+F-keys, as the `XF86Switch_VT_*` keysyms.
+pub fn vt_switch(handle: &KeysymHandle<'_>) -> Option<i32> {
+    let keysym = handle.modified_sym();
+    (keysyms::KEY_XF86Switch_VT_1..=keysyms::KEY_XF86Switch_VT_12)
+        .contains(&keysym)
+        .then(|| (keysym - keysyms::KEY_XF86Switch_VT_1 + 1) as i32)
+}
+
+impl KeyBinding {
+    pub fn matches(&self, modifiers: &ModifiersState, handle: &KeysymHandle<'_>) -> bool {
+        self.combo.modifiers.matches(modifiers)
+            && (handle.raw_syms().contains(&self.combo.keysym)
+                || handle.modified_sym() == self.combo.keysym)
+    }
+}
+
+/// Bindings available without a config: Super+number switches workspaces, Super+Shift+number
+/// moves the focused window there, Super+arrows focus, Super+Shift+arrows move and
+/// Super+Ctrl+arrows resize windows.
+pub fn default_bindings() -> Vec<KeyBinding> {
+    let mut bindings = [
+        ("Super+Shift+q", "close"),
+        ("Super+Shift+e", "quit"),
+        ("Super+Shift+c", "reload"),
+        ("Super+space", "toggle-floating"),
+        ("Super+m", "toggle-maximize"),
+        ("Super+f", "toggle-fullscreen"),
+        ("Super+Shift+space", "switch-layout"),
+    ]
+    .into_iter()
+    .map(|(combo, action)| (combo.to_string(), action.to_string()))
+    .collect::<Vec<_>>();
+
+    for workspace in 1..=WORKSPACE_COUNT {
+        bindings.push((
+            format!("Super+{}", workspace),
+            format!("workspace {}", workspace),
+        ));
+        bindings.push((
+            format!("Super+Shift+{}", workspace),
+            format!("move-to-workspace {}", workspace),
+        ));
+    }
+
+    for direction in ["Left", "Right", "Up", "Down"] {
+        let name = direction.to_ascii_lowercase();
+        bindings.push((format!("Super+{}", direction), format!("focus {}", name)));
+        bindings.push((
+            format!("Super+Shift+{}", direction),
+            format!("move {}", name),
+        ));
+        bindings.push((
+            format!("Super+Ctrl+{}", direction),
+            format!("resize {}", name),
+        ));
+    }
+
+    bindings
+        .into_iter()
+        .map(|(combo, action)| KeyBinding {
+            combo: combo.parse().unwrap(),
+            action: action.parse().unwrap(),
+        })
+        .collect()
+}
+
+fn center(geometry: Rectangle<i32, Logical>) -> Point<f64, Logical> {
+    geometry.loc.to_f64() + geometry.size.to_f64().downscale(2.0).to_point()
+}
+
+impl NoWayState {
+    pub fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Spawn(command) => self.spawn(&command),
+            Action::Close => {
+                if let Some(window) = self.focused_window() {
+                    window.send_close();
+                }
+            }
+            Action::Focus(direction) => {
+                let window = self
+                    .focused_window()
+                    .and_then(|window| self.window_in_direction(&window, direction));
+                if let Some(window) = window {
+                    self.focus_window(&window);
+                }
+            }
+            Action::Move(direction) => {
+                if let Some(window) = self.focused_window() {
+                    self.move_in_direction(&window, direction);
+                }
+            }
+            Action::Resize(direction) => {
+                if let Some(window) = self.focused_window() {
+                    self.resize_in_direction(&window, direction);
+                }
+            }
+            Action::SwitchWorkspace(workspace) => {
+                if let Some(output) = self.focused_output() {
+                    self.switch_workspace(&output, workspace);
+                }
+            }
+            Action::MoveToWorkspace(workspace) => {
+                if let Some(window) = self.focused_window() {
+                    self.move_window_to_workspace(&window, workspace);
+                }
+            }
+            Action::SetLayout(layout) => {
+                if let Some(output) = self.focused_output() {
+                    self.set_workspace_layout(&output, layout);
+                }
+            }
+            Action::ToggleFloating => {
+                if let Some(window) = self.focused_window() {
+                    self.toggle_floating(&window);
+                }
+            }
+            Action::ToggleMaximize => {
+                if let Some(window) = self.focused_window() {
+                    self.toggle_maximize(&window);
+                }
+            }
+            Action::ToggleFullscreen => {
+                if let Some(window) = self.focused_window() {
+                    self.toggle_fullscreen(&window);
+                }
+            }
+            Action::Minimize => {
+                if let Some(window) = self.focused_window() {
+                    self.minimize_window(&window);
+                }
+            }
+            Action::Unminimize => {
+                if let Some(output) = self.focused_output() {
+                    self.unminimize_window(&output);
+                }
+            }
+            Action::ConsumeIntoColumn => {
+                if let Some(window) = self.focused_window() {
+                    self.consume_into_column(&window);
+                }
+            }
+            Action::ExpelFromColumn => {
+                if let Some(window) = self.focused_window() {
+                    self.expel_from_column(&window);
+                }
+            }
+            Action::SwitchLayout => self.switch_keyboard_layout(),
+            Action::SwitchVt(vt) => self.switch_vt(vt),
+            Action::Quit => {
+                tracing::info!("Quitting");
+                self.loop_signal.stop();
+            }
+            Action::Reload => self.reload_config(),
+        }
+    }
+
+    /// Mapped window closest to the window in the direction, measured between their centers.
+    fn window_in_direction(
+        &self,
+        window: &WindowElement,
+        direction: Direction,
+    ) -> Option<WindowElement> {
+        let from = center(self.space.element_geometry(window)?);
+        self.space
+            .elements()
+            .filter(|w| w != &window && !w.is_override_redirect())
+            .filter_map(|w| Some((w, center(self.space.element_geometry(w)?) - from)))
+            .filter(|(_, offset)| match direction {
+                Direction::Left => offset.x < 0.0,
+                Direction::Right => offset.x > 0.0,
+                Direction::Up => offset.y < 0.0,
+                Direction::Down => offset.y > 0.0,
+            })
+            .min_by(|(_, a), (_, b)| (a.x * a.x + a.y * a.y).total_cmp(&(b.x * b.x + b.y * b.y)))
+            .map(|(w, _)| w.clone())
+    }
+
+    /// Whether the layout decides the geometry of the window.
+    pub fn is_tiled(&mut self, window: &WindowElement) -> bool {
+        if is_floating(window) || window.is_maximized() || window.is_fullscreen() {
+            return false;
+        }
+
+        let Some(output) = self.output_for_window(window) else {
+            return false;
+        };
+        let workspace = self.workspaces.active(&output);
+        self.workspace_layout(workspace) != LayoutKind::Floating
+    }
+
+    fn move_in_direction(&mut self, window: &WindowElement, direction: Direction) {
+        if self.is_tiled(window) {
+            let other = self
+                .window_in_direction(window, direction)
+                .filter(|other| self.is_tiled(other));
+            if let Some(other) = other {
+                self.swap_tiled(window, &other);
+            }
+            return;
+        }
+
+        let Some(location) = self.space.element_location(window) else {
+            return;
+        };
+        let offset: Point<i32, Logical> = match direction {
+            Direction::Left => (-KEYBOARD_STEP, 0),
+            Direction::Right => (KEYBOARD_STEP, 0),
+            Direction::Up => (0, -KEYBOARD_STEP),
+            Direction::Down => (0, KEYBOARD_STEP),
+        }
+        .into();
+        self.space
+            .map_element(window.clone(), location + offset, true);
+        if let WindowElement::X11(x11) = window {
+            let geometry = Rectangle::from_loc_and_size(location + offset, x11.geometry().size);
+            if let Err(err) = x11.configure(geometry) {
+                tracing::warn!("Failed to configure X11 window: {}", err);
+            }
+        }
+    }
+
+    /// Ends moving a window with the pointer or a touch point. A tiled window swaps tiles with the tiled window
+    /// it was dropped on, or goes back to its own tile.
+    pub fn drop_window(
+        &mut self,
+        window: &WindowElement,
+        initial_location: Point<i32, Logical>,
+        location: Point<f64, Logical>,
+    ) {
+        let Some(dropped_at) = self.space.element_location(window) else {
+            return;
+        };
+        // Whether and where the window is tiled depends on where it was before it was picked up.
+        self.space
+            .map_element(window.clone(), initial_location, false);
+        if !self.is_tiled(window) {
+            self.space.map_element(window.clone(), dropped_at, false);
+            return;
+        }
+
+        let output = self.output_for_window(window);
+        let target = self
+            .space
+            .elements()
+            .rev()
+            .filter(|other| *other != window)
+            .find(|other| {
+                self.space
+                    .element_geometry(other)
+                    .map_or(false, |geometry| geometry.to_f64().contains(location))
+            })
+            .cloned();
+        let target = target
+            .filter(|other| self.output_for_window(other) == output)
+            .filter(|other| self.is_tiled(other));
+        match (target, output) {
+            (Some(target), _) => self.swap_tiled(window, &target),
+            (None, Some(output)) => self.arrange_output(&output),
+            (None, None) => {}
+        }
+    }
+
+    /// Resizes floating windows, tiled ones are sized by the layout.
+    fn resize_in_direction(&mut self, window: &WindowElement, direction: Direction) {
+        if self.is_tiled(window) {
+            return;
+        }
+        let Some(location) = self.space.element_location(window) else {
+            return;
+        };
+
+        let size = window.geometry().size;
+        let (w, h) = match direction {
+            Direction::Left => (size.w - KEYBOARD_STEP, size.h),
+            Direction::Right => (size.w + KEYBOARD_STEP, size.h),
+            Direction::Up => (size.w, size.h - KEYBOARD_STEP),
+            Direction::Down => (size.w, size.h + KEYBOARD_STEP),
+        };
+        let size = (w.max(1), h.max(1)).into();
+
+        match window {
+            WindowElement::Xdg(xdg) => {
+                xdg.toplevel().with_pending_state(|state| {
+                    state.size = Some(size);
+                });
+                xdg.toplevel().send_configure();
+            }
+            WindowElement::X11(x11) => {
+                if let Err(err) = x11.configure(Rectangle::from_loc_and_size(location, size)) {
+                    tracing::warn!("Failed to configure X11 window: {}", err);
+                }
+            }
+        }
+    }
+}
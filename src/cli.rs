@@ -0,0 +1,173 @@
+use std::{fmt, path::PathBuf};
+
+use crate::backend::{BackendKind, RendererKind};
+
+pub const USAGE: &str = "\
+Usage: noway [OPTIONS]
+
+Options:
+  -b, --backend <BACKEND>   Backend to run on: winit, x11, udev or headless
+  -r, --renderer <RENDERER> Renderer to use: gles, or software for Mesa's llvmpipe; not
+                            supported by the x11 backend
+  -c, --config <PATH>       Config file to use instead of $XDG_CONFIG_HOME/noway/config.conf
+  -s, --socket <NAME>       Name of the Wayland socket to listen on
+      --command <COMMAND>   Command to spawn once the compositor is running
+      --validate-config     Check the config file for errors and exit
+  -V, --version             Print version and exit
+  -h, --help                Print this help and exit";
+
+#[derive(Debug)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.0, USAGE)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub backend: Option<BackendKind>,
+    pub renderer: Option<RendererKind>,
+    pub config_path: Option<PathBuf>,
+    pub socket_name: Option<String>,
+    pub command: Option<String>,
+    pub validate_config: bool,
+    pub version: bool,
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, CliError> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Both `--option value` and `--option=value` are accepted.
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError(format!("missing value for `{}`", name)))
+            };
+
+            match name.as_str() {
+                "-b" | "--backend" => {
+                    let backend = value()?;
+                    parsed.backend = Some(
+                        backend
+                            .parse()
+                            .map_err(|_| CliError(format!("unknown backend: {}", backend)))?,
+                    );
+                }
+                "-r" | "--renderer" => {
+                    let renderer = value()?;
+                    parsed.renderer = Some(
+                        renderer
+                            .parse()
+                            .map_err(|_| CliError(format!("unknown renderer: {}", renderer)))?,
+                    );
+                }
+                "-c" | "--config" => parsed.config_path = Some(value()?.into()),
+                "-s" | "--socket" => parsed.socket_name = Some(value()?),
+                "--command" => parsed.command = Some(value()?),
+                "--validate-config" => parsed.validate_config = true,
+                "-V" | "--version" => parsed.version = true,
+                "-h" | "--help" => parsed.help = true,
+                _ => return Err(CliError(format!("unknown argument: {}", name))),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, CliError> {
+        Args::parse_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse(args).unwrap_err().0
+    }
+
+    #[test]
+    fn parses_options() {
+        let args = parse(&[
+            "-b",
+            "headless",
+            "--renderer=software",
+            "--config",
+            "/tmp/noway.conf",
+            "-s",
+            "wayland-5",
+            "--command",
+            "foot --server",
+            "--validate-config",
+        ])
+        .unwrap();
+
+        assert_eq!(args.backend, Some(BackendKind::Headless));
+        assert_eq!(args.renderer, Some(RendererKind::Software));
+        assert_eq!(args.config_path, Some(PathBuf::from("/tmp/noway.conf")));
+        assert_eq!(args.socket_name.as_deref(), Some("wayland-5"));
+        assert_eq!(args.command.as_deref(), Some("foot --server"));
+        assert!(args.validate_config);
+        assert!(!args.version && !args.help);
+    }
+
+    #[test]
+    fn no_arguments_leave_defaults() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.backend, None);
+        assert_eq!(args.renderer, None);
+        assert!(args.command.is_none());
+    }
+
+    #[test]
+    fn inline_values_may_contain_equal_signs() {
+        let args = parse(&["--command=env A=1 foot"]).unwrap();
+        assert_eq!(args.command.as_deref(), Some("env A=1 foot"));
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        assert_eq!(error(&["--backend"]), "missing value for `--backend`");
+        assert_eq!(error(&["-c"]), "missing value for `-c`");
+        assert_eq!(
+            error(&["--help", "--command"]),
+            "missing value for `--command`"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_arguments_and_values() {
+        assert_eq!(error(&["--verbose"]), "unknown argument: --verbose");
+        assert_eq!(error(&["-b", "wayland"]), "unknown backend: wayland");
+        assert_eq!(error(&["--renderer=pixman"]), "unknown renderer: pixman");
+        // Short options do not take inline values.
+        assert_eq!(error(&["-b=udev"]), "unknown argument: -b=udev");
+    }
+
+    #[test]
+    fn errors_include_usage() {
+        let message = parse(&["--verbose"]).unwrap_err().to_string();
+        assert!(message.ends_with(USAGE));
+    }
+}
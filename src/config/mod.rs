@@ -0,0 +1,424 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use smithay::utils::{Logical, Point};
+
+use crate::{
+    bindings::{default_bindings, KeyBinding, Modifiers},
+    layout::LayoutKind,
+    placement::PlacementStrategy,
+};
+
+use self::parser::Entry;
+
+pub mod parser;
+pub mod reload;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse {
+        path: Option<PathBuf>,
+        line: usize,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    pub fn parse(line: usize, message: impl Into<String>) -> Self {
+        Self::Parse {
+            path: None,
+            line,
+            message: message.into(),
+        }
+    }
+
+    fn with_path(self, path: &Path) -> Self {
+        match self {
+            Self::Parse { line, message, .. } => Self::Parse {
+                path: Some(path.to_path_buf()),
+                line,
+                message,
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "failed to read {}: {}", path.display(), error),
+            Self::Parse {
+                path: Some(path),
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            Self::Parse {
+                path: None,
+                line,
+                message,
+            } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardConfig {
+    /// Delay before a held key starts repeating, in milliseconds.
+    pub repeat_delay: i32,
+    /// Number of repeated keys per second.
+    pub repeat_rate: i32,
+    pub rules: String,
+    pub model: String,
+    /// Layouts to switch between, the first one is active at startup.
+    pub layouts: Vec<String>,
+    /// Variant of every layout, by index.
+    pub variants: Vec<String>,
+    pub options: Option<String>,
+}
+
+impl KeyboardConfig {
+    /// Whether both configs result in the same keymaps.
+    pub fn same_keymap(&self, other: &Self) -> bool {
+        self.rules == other.rules
+            && self.model == other.model
+            && self.layouts == other.layouts
+            && self.variants == other.variants
+            && self.options == other.options
+    }
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            repeat_delay: 200,
+            repeat_rate: 50,
+            rules: String::new(),
+            model: String::new(),
+            layouts: Vec::new(),
+            variants: Vec::new(),
+            options: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputConfig {
+    pub background: [f32; 4],
+    /// Refresh rate of virtual outputs, in mHz.
+    pub refresh: i32,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            background: [0.1, 0.1, 0.1, 1.0],
+            refresh: 60_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutConfig {
+    /// Layout of workspaces that did not choose another one.
+    pub default: LayoutKind,
+    /// Space between tiled windows.
+    pub inner_gap: i32,
+    /// Space between tiled windows and the edges of the output.
+    pub outer_gap: i32,
+    /// Share of the output width taken by the master window.
+    pub master_ratio: f64,
+    /// Share of the output width taken by a column of the scrolling layout.
+    pub column_width: f64,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            default: LayoutKind::Floating,
+            inner_gap: 8,
+            outer_gap: 8,
+            master_ratio: 0.55,
+            column_width: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// File this config is loaded from and watched for changes.
+    pub path: Option<PathBuf>,
+    pub seat_name: String,
+    pub keyboard: KeyboardConfig,
+    pub output: OutputConfig,
+    pub initial_pointer_location: Point<f64, Logical>,
+    /// Modifier that lets any window be moved with the left and resized with the right button.
+    pub drag_modifier: Modifiers,
+    /// Name of the output touchscreens are mapped to, the first output if unset.
+    pub touch_output: Option<String>,
+    pub placement: PlacementStrategy,
+    pub layout: LayoutConfig,
+    /// Whether clients are asked to let the compositor draw title bars and borders.
+    pub server_side_decorations: bool,
+    pub xwayland: bool,
+    /// Key bindings, the defaults extended or overridden by the `[bindings]` section.
+    pub bindings: Vec<KeyBinding>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: None,
+            seat_name: "winit".to_string(),
+            keyboard: KeyboardConfig::default(),
+            output: OutputConfig::default(),
+            initial_pointer_location: (100.0, 100.0).into(),
+            drag_modifier: Modifiers {
+                logo: true,
+                ..Default::default()
+            },
+            touch_output: None,
+            placement: PlacementStrategy::default(),
+            layout: LayoutConfig::default(),
+            server_side_decorations: true,
+            xwayland: true,
+            bindings: default_bindings(),
+        }
+    }
+}
+
+impl Config {
+    /// Default location of the config file: `$XDG_CONFIG_HOME/noway/config.conf`.
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config_home| config_home.join("noway").join("config.conf"))
+    }
+
+    /// Loads the config from the default path, falling back to defaults if there is no file.
+    pub fn load() -> Result<Self, ConfigError> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            path => {
+                tracing::info!("No config file found, using defaults");
+                Ok(Self {
+                    path,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        tracing::info!("Loading config from {}", path.display());
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        let config = Self::parse(&source).map_err(|err| err.with_path(path))?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..config
+        })
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        for entry in parser::parse(source)? {
+            config.apply(&entry)?;
+        }
+
+        Ok(config)
+    }
+
+    fn apply(&mut self, entry: &Entry) -> Result<(), ConfigError> {
+        match (entry.section.as_str(), entry.key.as_str()) {
+            ("seat", "name") => {
+                let name = entry.string();
+                if name.is_empty() {
+                    return Err(entry.error("seat name must not be empty"));
+                }
+                self.seat_name = name;
+            }
+            ("keyboard", "repeat_delay") => {
+                self.keyboard.repeat_delay = entry.parse()?;
+                if self.keyboard.repeat_delay <= 0 {
+                    return Err(entry.error("repeat_delay must be positive"));
+                }
+            }
+            ("keyboard", "repeat_rate") => {
+                self.keyboard.repeat_rate = entry.parse()?;
+                if self.keyboard.repeat_rate < 0 {
+                    return Err(entry.error("repeat_rate must not be negative"));
+                }
+            }
+            ("keyboard", "rules") => self.keyboard.rules = entry.string(),
+            ("keyboard", "model") => self.keyboard.model = entry.string(),
+            ("keyboard", "layout") => self.keyboard.layouts = entry.list()?,
+            ("keyboard", "variant") => self.keyboard.variants = entry.list()?,
+            ("keyboard", "options") => self.keyboard.options = Some(entry.string()),
+            ("output", "background") => self.output.background = entry.color()?,
+            ("output", "refresh") => {
+                self.output.refresh = entry.parse()?;
+                if self.output.refresh <= 0 {
+                    return Err(entry.error("refresh must be positive"));
+                }
+            }
+            ("pointer", "initial_location") => match entry.list::<f64>()?[..] {
+                [x, y] => self.initial_pointer_location = (x, y).into(),
+                _ => return Err(entry.error("initial_location expects `x, y`")),
+            },
+            ("pointer", "drag_modifier") => {
+                self.drag_modifier = entry.string().parse().map_err(|_| {
+                    entry.error("drag_modifier must be modifiers like `Super` or `Ctrl+Alt`")
+                })?
+            }
+            ("touch", "output") => self.touch_output = Some(entry.string()),
+            ("placement", "strategy") => {
+                self.placement = entry.string().parse().map_err(|_| {
+                    entry.error("strategy must be one of `center`, `cascade` or `free_area`")
+                })?
+            }
+            ("layout", "default") => {
+                self.layout.default = entry.string().parse().map_err(|_| {
+                    entry.error(
+                        "default must be one of `floating`, `master_stack`, `dwindle` or `scrolling`",
+                    )
+                })?
+            }
+            ("layout", "inner_gap") => {
+                self.layout.inner_gap = entry.parse()?;
+                if self.layout.inner_gap < 0 {
+                    return Err(entry.error("inner_gap must not be negative"));
+                }
+            }
+            ("layout", "outer_gap") => {
+                self.layout.outer_gap = entry.parse()?;
+                if self.layout.outer_gap < 0 {
+                    return Err(entry.error("outer_gap must not be negative"));
+                }
+            }
+            ("layout", "master_ratio") => {
+                self.layout.master_ratio = entry.parse()?;
+                if !(0.1..=0.9).contains(&self.layout.master_ratio) {
+                    return Err(entry.error("master_ratio must be between 0.1 and 0.9"));
+                }
+            }
+            ("layout", "column_width") => {
+                self.layout.column_width = entry.parse()?;
+                if !(0.1..=1.0).contains(&self.layout.column_width) {
+                    return Err(entry.error("column_width must be between 0.1 and 1.0"));
+                }
+            }
+            ("decoration", "server_side") => self.server_side_decorations = entry.boolean()?,
+            ("xwayland", "enabled") => self.xwayland = entry.boolean()?,
+            ("bindings", key) => {
+                let combo = key
+                    .parse()
+                    .map_err(|_| entry.error(format!("invalid key combination `{}`", key)))?;
+                self.bindings.retain(|binding| binding.combo != combo);
+
+                // `none` removes a default binding.
+                let value = entry.string();
+                if value != "none" {
+                    let action = value
+                        .parse()
+                        .map_err(|_| entry.error(format!("invalid action `{}`", value)))?;
+                    self.bindings.push(KeyBinding { combo, action });
+                }
+            }
+            ("", key) => return Err(entry.error(format!("`{}` is outside of any section", key))),
+            (section, key) => {
+                return Err(entry.error(format!("unknown option `{}` in [{}]", key, section)))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::KeyCombo;
+
+    fn error_line(source: &str) -> usize {
+        match Config::parse(source) {
+            Err(ConfigError::Parse { line, .. }) => line,
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn empty_config_is_default() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn applies_known_options() {
+        let config = Config::parse(
+            "\
+[keyboard]
+layout = us, de
+variant = ,nodeadkeys
+
+[pointer]
+initial_location = 10, 20
+
+[layout]
+default = dwindle
+inner_gap = 0
+
+[xwayland]
+enabled = no
+",
+        )
+        .unwrap();
+
+        assert_eq!(config.keyboard.layouts, ["us", "de"]);
+        assert_eq!(config.keyboard.variants, ["", "nodeadkeys"]);
+        assert_eq!(config.initial_pointer_location, (10.0, 20.0).into());
+        assert_eq!(config.layout.default, LayoutKind::Dwindle);
+        assert_eq!(config.layout.inner_gap, 0);
+        assert!(!config.xwayland);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_sections() {
+        assert_eq!(error_line("[keyboard]\nrepeat = 1"), 2);
+        assert_eq!(error_line("[seat]\nname = seat0\n[unknown]\nname = x"), 4);
+        assert_eq!(error_line("name = seat0"), 1);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert_eq!(error_line("[keyboard]\nrepeat_delay = 0"), 2);
+        assert_eq!(error_line("[keyboard]\nrepeat_rate = fast"), 2);
+        assert_eq!(error_line("[pointer]\ninitial_location = 1, 2, 3"), 2);
+        assert_eq!(error_line("[layout]\nmaster_ratio = 0.95"), 2);
+        assert_eq!(error_line("[layout]\ndefault = spiral"), 2);
+        assert_eq!(error_line("[output]\nbackground = red"), 2);
+        assert_eq!(error_line("[bindings]\nSuper+q = explode"), 2);
+    }
+
+    #[test]
+    fn bindings_override_and_remove_defaults() {
+        let config = Config::parse("[bindings]\nSuper+f = close\nSuper+m = none").unwrap();
+        let action = |combo: &str| {
+            let combo: KeyCombo = combo.parse().unwrap();
+            config
+                .bindings
+                .iter()
+                .find(|binding| binding.combo == combo)
+                .map(|binding| binding.action.clone())
+        };
+
+        assert_eq!(action("Super+f"), Some("close".parse().unwrap()));
+        assert_eq!(action("Super+m"), None);
+    }
+}
@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+use super::ConfigError;
+
+/// A single `key = value` line of the config file, together with the section it belongs to.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub line: usize,
+    pub section: String,
+    pub key: String,
+    pub value: String,
+}
+
+impl Entry {
+    pub fn error(&self, message: impl Into<String>) -> ConfigError {
+        ConfigError::parse(self.line, message)
+    }
+
+    pub fn string(&self) -> String {
+        unquote(&self.value).to_string()
+    }
+
+    pub fn parse<T: FromStr>(&self) -> Result<T, ConfigError> {
+        self.string()
+            .parse()
+            .map_err(|_| self.error(format!("invalid value for `{}`: {}", self.key, self.value)))
+    }
+
+    pub fn boolean(&self) -> Result<bool, ConfigError> {
+        match self.string().as_str() {
+            "true" | "yes" | "on" => Ok(true),
+            "false" | "no" | "off" => Ok(false),
+            _ => Err(self.error(format!("expected a boolean for `{}`", self.key))),
+        }
+    }
+
+    /// Parses a comma separated list of values, e.g. `100, 100`.
+    pub fn list<T: FromStr>(&self) -> Result<Vec<T>, ConfigError> {
+        self.string()
+            .split(',')
+            .map(|item| {
+                item.trim().parse().map_err(|_| {
+                    self.error(format!("invalid list item for `{}`: {}", self.key, item))
+                })
+            })
+            .collect()
+    }
+
+    /// Parses a `#rrggbb` or `#rrggbbaa` color into normalized RGBA components.
+    pub fn color(&self) -> Result<[f32; 4], ConfigError> {
+        let value = self.string();
+        let invalid = || self.error(format!("invalid color for `{}`: {}", self.key, value));
+
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let mut color = [1.0; 4];
+        for (index, component) in color.iter_mut().enumerate().take(hex.len() / 2) {
+            let byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap();
+            *component = byte as f32 / 255.0;
+        }
+
+        Ok(color)
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Splits the config source into entries.
+///
+/// The format is a flat list of `[section]` headers followed by `key = value` lines. Lines
+/// starting with `#` are comments.
+pub fn parse(source: &str) -> Result<Vec<Entry>, ConfigError> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| ConfigError::parse(line_number, "unterminated section header"))?
+                .trim();
+            if name.is_empty() {
+                return Err(ConfigError::parse(line_number, "empty section name"));
+            }
+
+            section = name.to_string();
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| ConfigError::parse(line_number, "expected `key = value`"))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ConfigError::parse(line_number, "missing key before `=`"));
+        }
+
+        entries.push(Entry {
+            line: line_number,
+            section: section.clone(),
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(value: &str) -> Entry {
+        Entry {
+            line: 3,
+            section: "output".to_string(),
+            key: "background".to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn error_line(error: ConfigError) -> usize {
+        match error {
+            ConfigError::Parse { line, .. } => line,
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn parses_sections_and_entries() {
+        let source = "\
+# comment
+[keyboard]
+repeat_delay = 300
+
+  [ output ]
+background=\"#102030\"
+";
+        let entries = parse(source).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].line, 3);
+        assert_eq!(entries[0].section, "keyboard");
+        assert_eq!(entries[0].key, "repeat_delay");
+        assert_eq!(entries[0].value, "300");
+
+        assert_eq!(entries[1].line, 6);
+        assert_eq!(entries[1].section, "output");
+        assert_eq!(entries[1].string(), "#102030");
+    }
+
+    #[test]
+    fn entries_before_any_section_have_no_section() {
+        let entries = parse("key = value").unwrap();
+        assert_eq!(entries[0].section, "");
+    }
+
+    #[test]
+    fn values_keep_equal_signs() {
+        let entries = parse("[bindings]\nSuper+Return = spawn env A=1 foot").unwrap();
+        assert_eq!(entries[0].key, "Super+Return");
+        assert_eq!(entries[0].value, "spawn env A=1 foot");
+    }
+
+    #[test]
+    fn rejects_malformed_lines_with_their_line_number() {
+        assert_eq!(error_line(parse("[keyboard]\n[output").unwrap_err()), 2);
+        assert_eq!(error_line(parse("\n\n[ ]").unwrap_err()), 3);
+        assert_eq!(error_line(parse("[seat]\nname").unwrap_err()), 2);
+        assert_eq!(error_line(parse("[seat]\n = winit").unwrap_err()), 2);
+    }
+
+    #[test]
+    fn parses_booleans() {
+        for value in ["true", "yes", "on"] {
+            assert!(entry(value).boolean().unwrap());
+        }
+        for value in ["false", "no", "off"] {
+            assert!(!entry(value).boolean().unwrap());
+        }
+        assert_eq!(error_line(entry("maybe").boolean().unwrap_err()), 3);
+    }
+
+    #[test]
+    fn parses_lists() {
+        assert_eq!(entry("1, 2,3").list::<i32>().unwrap(), vec![1, 2, 3]);
+        assert_eq!(entry("us,,de").list::<String>().unwrap(), ["us", "", "de"]);
+        assert!(entry("1, x").list::<i32>().is_err());
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(entry("#ff0000").color().unwrap(), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(entry("#00000000").color().unwrap(), [0.0; 4]);
+        for value in ["ff0000", "#ff00", "#gg0000", "#ff0000ff00"] {
+            assert!(entry(value).color().is_err(), "{} is not a color", value);
+        }
+    }
+
+    #[test]
+    fn rejects_unparsable_values() {
+        assert_eq!(entry("42").parse::<i32>().unwrap(), 42);
+        assert_eq!(error_line(entry("fast").parse::<i32>().unwrap_err()), 3);
+    }
+}
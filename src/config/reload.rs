@@ -0,0 +1,110 @@
+use std::os::fd::AsRawFd;
+
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use smithay::reexports::calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction};
+
+use crate::{
+    render::notice::Notice,
+    state::{NoWayData, NoWayState},
+};
+
+use super::Config;
+
+/// Watches the config file and reloads it whenever it is created, modified or removed.
+///
+/// The directory of the file is watched rather than the file itself, so the file being replaced
+/// by an editor or created later is noticed as well.
+pub fn watch_config(
+    handle: &LoopHandle<'static, NoWayData>,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = config.path.clone() else {
+        return Ok(());
+    };
+    let (Some(directory), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Ok(());
+    };
+
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+    let flags = AddWatchFlags::IN_CLOSE_WRITE
+        | AddWatchFlags::IN_MOVED_TO
+        | AddWatchFlags::IN_MOVED_FROM
+        | AddWatchFlags::IN_DELETE;
+    if let Err(err) = inotify.add_watch(directory, flags) {
+        tracing::warn!("Not watching {} for changes: {}", directory.display(), err);
+        return Ok(());
+    }
+
+    let file_name = file_name.to_os_string();
+    handle.insert_source(
+        Generic::new(inotify.as_raw_fd(), Interest::READ, Mode::Level),
+        move |_, _, data| {
+            let events = inotify.read_events().unwrap_or_default();
+            if events
+                .iter()
+                .any(|event| event.name.as_ref() == Some(&file_name))
+            {
+                data.state.reload_config();
+            }
+
+            Ok(PostAction::Continue)
+        },
+    )?;
+
+    Ok(())
+}
+
+impl NoWayState {
+    /// Reloads the config from the file it was loaded from. Errors are logged and shown as an
+    /// on-screen notice, the running config is kept in that case.
+    pub fn reload_config(&mut self) {
+        let Some(path) = self.config.path.clone() else {
+            return;
+        };
+
+        let config = if path.exists() {
+            Config::load_from(&path)
+        } else {
+            Ok(Config {
+                path: Some(path),
+                ..Default::default()
+            })
+        };
+
+        match config {
+            Ok(config) => {
+                self.config_notice = None;
+                self.apply_config(config);
+            }
+            Err(err) => {
+                tracing::error!("Failed to reload config: {}", err);
+                self.config_notice = Some(Notice::new(err.to_string()));
+            }
+        }
+    }
+
+    pub fn apply_config(&mut self, config: Config) {
+        if config.seat_name != self.config.seat_name {
+            tracing::warn!("Changing the seat name requires restarting NoWay");
+        }
+
+        if config.keyboard != self.config.keyboard {
+            self.seat
+                .get_keyboard()
+                .unwrap()
+                .change_repeat_info(config.keyboard.repeat_rate, config.keyboard.repeat_delay);
+        }
+
+        let keymap_changed = !config.keyboard.same_keymap(&self.config.keyboard);
+        let layout_changed = config.layout != self.config.layout;
+        self.config = config;
+        self.config_changed = true;
+        if keymap_changed {
+            self.set_keyboard_layout(0);
+        }
+        if layout_changed {
+            self.arrange_all();
+        }
+        tracing::info!("Applied new config");
+    }
+}
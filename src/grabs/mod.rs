@@ -4,6 +4,7 @@ use self::resize_grab::ResizeState;
 
 pub mod move_grab;
 pub mod resize_grab;
+pub mod touch_grab;
 
 #[derive(Default)]
 pub struct SurfaceData {
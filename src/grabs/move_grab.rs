@@ -4,7 +4,7 @@ use smithay::{
         RelativeMotionEvent,
     },
     reexports::wayland_server::protocol::wl_surface::WlSurface,
-    utils::{Logical, Point},
+    utils::{Logical, Point, Rectangle},
 };
 
 use crate::{render::window::WindowElement, state::NoWayState};
@@ -26,10 +26,22 @@ impl PointerGrab<NoWayState> for MoveSurfaceGrab {
         // While the grab is active, no client has pointer focus
         handle.motion(data, None, event);
 
+        // Moving would map the window again after it was hidden, e.g. with its workspace.
+        if data.space.element_location(&self.window).is_none() {
+            handle.unset_grab(data, event.serial, event.time);
+            return;
+        }
+
         let delta = event.location - self.start_data.location;
-        let new_location = self.initial_window_location.to_f64() + delta;
+        let new_location = (self.initial_window_location.to_f64() + delta).to_i32_round();
         data.space
-            .map_element(self.window.clone(), new_location.to_i32_round(), true);
+            .map_element(self.window.clone(), new_location, true);
+        if let WindowElement::X11(x11) = &self.window {
+            let geometry = Rectangle::from_loc_and_size(new_location, x11.geometry().size);
+            if let Err(err) = x11.configure(geometry) {
+                tracing::warn!("Failed to configure X11 window: {}", err);
+            }
+        }
     }
 
     fn relative_motion(
@@ -57,6 +69,11 @@ impl PointerGrab<NoWayState> for MoveSurfaceGrab {
         if !handle.current_pressed().contains(&BTN_LEFT) {
             // No more buttons are pressed, release the grab.
             handle.unset_grab(data, event.serial, event.time);
+            data.drop_window(
+                &self.window,
+                self.initial_window_location,
+                handle.current_location(),
+            );
         }
     }
 
@@ -89,6 +89,48 @@ pub enum ResizeState {
     WaitingForCommit(ResizeData),
 }
 
+/// Forgets the resize of a window that is no longer mapped, e.g. because its workspace was
+/// hidden in the middle of the resize.
+pub fn cancel_resize(window: &WindowElement) {
+    let Some(surface) = window.wl_surface() else {
+        return;
+    };
+    let resizing = with_states(&surface, |states| {
+        let Some(data) = states.data_map.get::<RefCell<SurfaceData>>() else {
+            return false;
+        };
+        let mut data = data.borrow_mut();
+        let resizing = matches!(data.resize_state, ResizeState::Resizing(_));
+        if resizing {
+            data.resize_state = ResizeState::NotResizing;
+        }
+        resizing
+    });
+    if !resizing {
+        return;
+    }
+
+    if let WindowElement::Xdg(w) = window {
+        let xdg = w.toplevel();
+        xdg.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Resizing);
+        });
+        xdg.send_configure();
+    }
+}
+
+/// Whether a grab is already resizing the surface, e.g. a touch point while the pointer tries to.
+pub fn is_resizing(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<SurfaceData>>()
+            .map_or(false, |data| {
+                matches!(data.borrow().resize_state, ResizeState::Resizing(_))
+            })
+    })
+}
+
 pub struct ResizeSurfaceGrab {
     pub start_data: GrabStartData<NoWayState>,
     pub window: WindowElement,
@@ -98,24 +140,54 @@ pub struct ResizeSurfaceGrab {
     pub last_window_size: Size<i32, Logical>,
 }
 
-impl PointerGrab<NoWayState> for ResizeSurfaceGrab {
-    fn motion(
-        &mut self,
-        data: &mut NoWayState,
-        handle: &mut PointerInnerHandle<'_, NoWayState>,
-        _focus: Option<(WlSurface, Point<i32, Logical>)>,
-        event: &MotionEvent,
-    ) {
-        // While the grab is active, no client has pointer focus
-        handle.motion(data, None, event);
-
-        // It is impossible to get `min_size` and `max_size` of dead toplevel, so we return early.
-        if !self.window.alive() {
-            handle.unset_grab(data, event.serial, event.time);
-            return;
+impl ResizeSurfaceGrab {
+    /// Creates a grab resizing `window` from its current geometry and marks the window as being
+    /// resized. Returns `None` if another grab is resizing the window already.
+    pub fn start(
+        start_data: GrabStartData<NoWayState>,
+        window: WindowElement,
+        edges: ResizeEdge,
+        initial_window_location: Point<i32, Logical>,
+    ) -> Option<Self> {
+        let surface = window.wl_surface()?;
+        if is_resizing(&surface) {
+            return None;
         }
+        let initial_window_size = window.geometry().size;
+
+        with_states(&surface, move |states| {
+            states
+                .data_map
+                .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+            states
+                .data_map
+                .get::<RefCell<SurfaceData>>()
+                .unwrap()
+                .borrow_mut()
+                .resize_state = ResizeState::Resizing(ResizeData {
+                edges,
+                initial_window_location,
+                initial_window_size,
+            });
+        });
+
+        Some(Self {
+            start_data,
+            window,
+            edges,
+            initial_window_location,
+            initial_window_size,
+            last_window_size: initial_window_size,
+        })
+    }
 
-        let (mut dx, mut dy) = (event.location - self.start_data.location).into();
+    /// Asks the window for the size it gets when the grab point moved to `location`.
+    pub fn resize_to(&mut self, data: &mut NoWayState, location: Point<f64, Logical>) {
+        // The window may have been unmapped meanwhile, e.g. with its workspace.
+        let Some(window_location) = data.space.element_location(&self.window) else {
+            return;
+        };
+        let (mut dx, mut dy) = (location - self.start_data.location).into();
 
         let mut new_window_width = self.initial_window_size.w;
         let mut new_window_height = self.initial_window_size.h;
@@ -176,15 +248,123 @@ impl PointerGrab<NoWayState> for ResizeSurfaceGrab {
                 xdg.send_configure();
             }
             WindowElement::X11(x11) => {
-                let location = data.space.element_location(&self.window).unwrap();
+                x11.configure(Rectangle::from_loc_and_size(
+                    window_location,
+                    self.last_window_size,
+                ))
+                .unwrap();
+            }
+        }
+    }
+
+    /// Ends the resize, moving the window when it was resized from the top or left.
+    pub fn finish(&mut self, data: &mut NoWayState, serial: Serial) {
+        // If toplevel is dead, we can't resize it, so we return early.
+        if !self.window.alive() {
+            return;
+        }
+        let Some(mut location) = data.space.element_location(&self.window) else {
+            cancel_resize(&self.window);
+            return;
+        };
+
+        match &self.window {
+            WindowElement::Xdg(w) => {
+                let xdg = w.toplevel();
+                xdg.with_pending_state(|state| {
+                    state.states.unset(xdg_toplevel::State::Resizing);
+                    state.size = Some(self.last_window_size);
+                });
+                xdg.send_configure();
+                if self.edges.intersects(ResizeEdge::TOP_LEFT) {
+                    let geometry = self.window.geometry();
+
+                    if self.edges.intersects(ResizeEdge::LEFT) {
+                        location.x = self.initial_window_location.x
+                            + (self.initial_window_size.w - geometry.size.w);
+                    }
+                    if self.edges.intersects(ResizeEdge::TOP) {
+                        location.y = self.initial_window_location.y
+                            + (self.initial_window_size.h - geometry.size.h);
+                    }
+
+                    data.space.map_element(self.window.clone(), location, true);
+                }
+
+                with_states(&self.window.wl_surface().unwrap(), |states| {
+                    let mut data = states
+                        .data_map
+                        .get::<RefCell<SurfaceData>>()
+                        .unwrap()
+                        .borrow_mut();
+                    if let ResizeState::Resizing(resize_data) = data.resize_state {
+                        data.resize_state = ResizeState::WaitingForFinalAck(resize_data, serial);
+                    } else {
+                        panic!("invalid resize state: {:?}", data.resize_state);
+                    }
+                });
+            }
+            WindowElement::X11(x11) => {
+                if self.edges.intersects(ResizeEdge::TOP_LEFT) {
+                    let geometry = self.window.geometry();
+
+                    if self.edges.intersects(ResizeEdge::LEFT) {
+                        location.x = self.initial_window_location.x
+                            + (self.initial_window_size.w - geometry.size.w);
+                    }
+                    if self.edges.intersects(ResizeEdge::TOP) {
+                        location.y = self.initial_window_location.y
+                            + (self.initial_window_size.h - geometry.size.h);
+                    }
+
+                    data.space.map_element(self.window.clone(), location, true);
+                }
                 x11.configure(Rectangle::from_loc_and_size(
                     location,
                     self.last_window_size,
                 ))
                 .unwrap();
+
+                let Some(surface) = self.window.wl_surface() else {
+                    // X11 Window got unmapped, abort
+                    return
+                };
+                with_states(&surface, |states| {
+                    let mut data = states
+                        .data_map
+                        .get::<RefCell<SurfaceData>>()
+                        .unwrap()
+                        .borrow_mut();
+                    if let ResizeState::Resizing(resize_data) = data.resize_state {
+                        data.resize_state = ResizeState::WaitingForCommit(resize_data);
+                    } else {
+                        panic!("invalid resize state: {:?}", data.resize_state);
+                    }
+                });
             }
         }
     }
+}
+
+impl PointerGrab<NoWayState> for ResizeSurfaceGrab {
+    fn motion(
+        &mut self,
+        data: &mut NoWayState,
+        handle: &mut PointerInnerHandle<'_, NoWayState>,
+        _focus: Option<(WlSurface, Point<i32, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // While the grab is active, no client has pointer focus
+        handle.motion(data, None, event);
+
+        // It is impossible to get `min_size` and `max_size` of dead toplevel, so we return early.
+        if !self.window.alive() {
+            handle.unset_grab(data, event.serial, event.time);
+            return;
+        }
+
+        self.resize_to(data, event.location);
+    }
 
     fn relative_motion(
         &mut self,
@@ -206,90 +386,7 @@ impl PointerGrab<NoWayState> for ResizeSurfaceGrab {
         if handle.current_pressed().is_empty() {
             // No more buttons are pressed, release the grab.
             handle.unset_grab(data, event.serial, event.time);
-
-            // If toplevel is dead, we can't resize it, so we return early.
-            if !self.window.alive() {
-                return;
-            }
-
-            match &self.window {
-                WindowElement::Xdg(w) => {
-                    let xdg = w.toplevel();
-                    xdg.with_pending_state(|state| {
-                        state.states.unset(xdg_toplevel::State::Resizing);
-                        state.size = Some(self.last_window_size);
-                    });
-                    xdg.send_configure();
-                    if self.edges.intersects(ResizeEdge::TOP_LEFT) {
-                        let geometry = self.window.geometry();
-                        let mut location = data.space.element_location(&self.window).unwrap();
-
-                        if self.edges.intersects(ResizeEdge::LEFT) {
-                            location.x = self.initial_window_location.x
-                                + (self.initial_window_size.w - geometry.size.w);
-                        }
-                        if self.edges.intersects(ResizeEdge::TOP) {
-                            location.y = self.initial_window_location.y
-                                + (self.initial_window_size.h - geometry.size.h);
-                        }
-
-                        data.space.map_element(self.window.clone(), location, true);
-                    }
-
-                    with_states(&self.window.wl_surface().unwrap(), |states| {
-                        let mut data = states
-                            .data_map
-                            .get::<RefCell<SurfaceData>>()
-                            .unwrap()
-                            .borrow_mut();
-                        if let ResizeState::Resizing(resize_data) = data.resize_state {
-                            data.resize_state =
-                                ResizeState::WaitingForFinalAck(resize_data, event.serial);
-                        } else {
-                            panic!("invalid resize state: {:?}", data.resize_state);
-                        }
-                    });
-                }
-                WindowElement::X11(x11) => {
-                    let mut location = data.space.element_location(&self.window).unwrap();
-                    if self.edges.intersects(ResizeEdge::TOP_LEFT) {
-                        let geometry = self.window.geometry();
-
-                        if self.edges.intersects(ResizeEdge::LEFT) {
-                            location.x = self.initial_window_location.x
-                                + (self.initial_window_size.w - geometry.size.w);
-                        }
-                        if self.edges.intersects(ResizeEdge::TOP) {
-                            location.y = self.initial_window_location.y
-                                + (self.initial_window_size.h - geometry.size.h);
-                        }
-
-                        data.space.map_element(self.window.clone(), location, true);
-                    }
-                    x11.configure(Rectangle::from_loc_and_size(
-                        location,
-                        self.last_window_size,
-                    ))
-                    .unwrap();
-
-                    let Some(surface) = self.window.wl_surface() else {
-                        // X11 Window got unmapped, abort
-                        return
-                    };
-                    with_states(&surface, |states| {
-                        let mut data = states
-                            .data_map
-                            .get::<RefCell<SurfaceData>>()
-                            .unwrap()
-                            .borrow_mut();
-                        if let ResizeState::Resizing(resize_data) = data.resize_state {
-                            data.resize_state = ResizeState::WaitingForCommit(resize_data);
-                        } else {
-                            panic!("invalid resize state: {:?}", data.resize_state);
-                        }
-                    });
-                }
-            }
+            self.finish(data, event.serial);
         }
     }
 
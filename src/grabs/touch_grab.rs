@@ -0,0 +1,121 @@
+use std::fmt;
+
+use smithay::{
+    backend::input::TouchSlot,
+    input::pointer::GrabStartData,
+    utils::{Logical, Point, Rectangle, Serial},
+};
+
+use crate::{render::window::WindowElement, state::NoWayState};
+
+use super::resize_grab::{ResizeEdge, ResizeSurfaceGrab};
+
+enum TouchGrabKind {
+    Move {
+        start_location: Point<f64, Logical>,
+        /// Last location of the touch point, where the window is dropped.
+        location: Point<f64, Logical>,
+        window: WindowElement,
+        initial_window_location: Point<i32, Logical>,
+    },
+    Resize(ResizeSurfaceGrab),
+}
+
+/// Moves or resizes a window following one touch point. The smithay version NoWay uses has no
+/// touch grabs, so the touch handlers feed the grab while it is active instead of the clients.
+pub struct TouchGrab {
+    pub slot: TouchSlot,
+    kind: TouchGrabKind,
+}
+
+impl fmt::Debug for TouchGrab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchGrab")
+            .field("slot", &self.slot)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TouchGrab {
+    pub fn start_move(
+        slot: TouchSlot,
+        start_location: Point<f64, Logical>,
+        window: WindowElement,
+        initial_window_location: Point<i32, Logical>,
+    ) -> Self {
+        Self {
+            slot,
+            kind: TouchGrabKind::Move {
+                start_location,
+                location: start_location,
+                window,
+                initial_window_location,
+            },
+        }
+    }
+
+    pub fn start_resize(
+        slot: TouchSlot,
+        start_location: Point<f64, Logical>,
+        window: WindowElement,
+        edges: ResizeEdge,
+        initial_window_location: Point<i32, Logical>,
+    ) -> Option<Self> {
+        // The resize only uses the start location, touch points have no button or pointer focus.
+        let start_data = GrabStartData {
+            focus: None,
+            button: 0,
+            location: start_location,
+        };
+        let grab = ResizeSurfaceGrab::start(start_data, window, edges, initial_window_location)?;
+
+        Some(Self {
+            slot,
+            kind: TouchGrabKind::Resize(grab),
+        })
+    }
+
+    pub fn window(&self) -> &WindowElement {
+        match &self.kind {
+            TouchGrabKind::Move { window, .. } => window,
+            TouchGrabKind::Resize(grab) => &grab.window,
+        }
+    }
+
+    pub fn motion(&mut self, data: &mut NoWayState, location: Point<f64, Logical>) {
+        match &mut self.kind {
+            TouchGrabKind::Move {
+                start_location,
+                location: last_location,
+                window,
+                initial_window_location,
+            } => {
+                *last_location = location;
+                let new_location = (initial_window_location.to_f64()
+                    + (location - *start_location))
+                    .to_i32_round();
+                data.space.map_element(window.clone(), new_location, true);
+                if let WindowElement::X11(x11) = window {
+                    let geometry = Rectangle::from_loc_and_size(new_location, x11.geometry().size);
+                    if let Err(err) = x11.configure(geometry) {
+                        tracing::warn!("Failed to configure X11 window: {}", err);
+                    }
+                }
+            }
+            TouchGrabKind::Resize(grab) => grab.resize_to(data, location),
+        }
+    }
+
+    /// Ends the grab once its touch point is lifted or cancelled.
+    pub fn up(&mut self, data: &mut NoWayState, serial: Serial) {
+        match &mut self.kind {
+            TouchGrabKind::Move {
+                location,
+                window,
+                initial_window_location,
+                ..
+            } => data.drop_window(window, *initial_window_location, *location),
+            TouchGrabKind::Resize(grab) => grab.finish(data, serial),
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use smithay::{
     backend::renderer::utils::on_commit_buffer_handler,
     delegate_compositor,
-    desktop::WindowSurfaceType,
+    desktop::{space::SpaceElement, WindowSurfaceType},
     input::pointer::PointerHandle,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
     utils::{Logical, Point},
-    wayland::compositor::{get_parent, is_sync_subsurface, CompositorHandler, CompositorState},
+    wayland::{
+        compositor::{get_parent, is_sync_subsurface, CompositorHandler, CompositorState},
+        shell::wlr_layer::Layer,
+    },
 };
 
 use crate::{render::window::WindowElement, state::NoWayState};
@@ -22,7 +25,25 @@ impl NoWayState {
         &self,
         pointer: &PointerHandle<Self>,
     ) -> Option<(&WindowElement, Point<i32, Logical>)> {
-        let pos = pointer.current_location();
+        self.window_under(pointer.current_location())
+    }
+
+    pub fn window_under(
+        &self,
+        pos: Point<f64, Logical>,
+    ) -> Option<(&WindowElement, Point<i32, Logical>)> {
+        // A fullscreen window covers its output, even if other windows were raised above it.
+        let fullscreen = self
+            .space
+            .output_under(pos)
+            .next()
+            .and_then(|output| self.fullscreen_window(output));
+        if let Some(window) = fullscreen {
+            // Like `Space::element_under`, return where the window is drawn.
+            let location = self.space.element_location(window)? - window.geometry().loc;
+            return Some((window, location));
+        }
+
         self.space.element_under(pos)
     }
 
@@ -30,13 +51,24 @@ impl NoWayState {
         &self,
         pointer: &PointerHandle<Self>,
     ) -> Option<(WlSurface, Point<i32, Logical>)> {
-        let pos = pointer.current_location();
-        self.window_under_pointer(pointer)
-            .and_then(|(window, location)| {
-                window
-                    .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
-                    .map(|(s, p)| (s, p + location))
-            })
+        self.surface_under(pointer.current_location())
+    }
+
+    pub fn surface_under(
+        &self,
+        pos: Point<f64, Logical>,
+    ) -> Option<(WlSurface, Point<i32, Logical>)> {
+        if let Some(under) = self.layer_surface_under(pos, &[Layer::Overlay, Layer::Top]) {
+            return Some(under);
+        }
+
+        if let Some((window, location)) = self.window_under(pos) {
+            return window
+                .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
+                .map(|(s, p)| (s, p + location));
+        }
+
+        self.layer_surface_under(pos, &[Layer::Bottom, Layer::Background])
     }
 }
 
@@ -53,12 +85,19 @@ impl CompositorHandler for NoWayState {
                 root = parent;
             }
 
-            if let Some(WindowElement::Xdg(window)) = self.window_for_surface(surface) {
+            let window = self
+                .window_for_surface(surface)
+                .or_else(|| self.workspaces.hidden_window_for_surface(surface).cloned());
+            if let Some(WindowElement::Xdg(window)) = window {
                 window.on_commit();
             }
+            self.map_pending_window(&root);
         };
 
+        self.popups.commit(surface);
         self.commit_xdg_surface(surface);
+        self.commit_layer_surface(surface);
+        self.commit_pointer_constraints(surface);
     }
 }
 
@@ -0,0 +1,373 @@
+//! `zwlr_data_control_manager_v1`, which lets clipboard managers read and set the selections.
+//! The smithay version NoWay uses does not implement it yet.
+
+use std::sync::Mutex;
+
+use smithay::{
+    reexports::{
+        wayland_protocols_wlr::data_control::v1::server::{
+            zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+            zwlr_data_control_manager_v1::{self, ZwlrDataControlManagerV1},
+            zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+            zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+        },
+        wayland_server::{
+            backend::{ClientId, ObjectId},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    wayland::{
+        data_device::{clear_data_device_selection, set_data_device_selection},
+        primary_selection::{clear_primary_selection, set_primary_selection},
+    },
+    xwayland::xwm::SelectionType,
+};
+
+use crate::{handlers::data_device::SelectionSource, state::NoWayState};
+
+/// Mime types of a data-control source, which can not change once it owns a selection.
+#[derive(Debug, Default)]
+pub struct DataControlSource {
+    mime_types: Vec<String>,
+    used: bool,
+}
+
+/// Selection a data-control offer advertised. Reading it fails once the selection changed.
+#[derive(Debug)]
+pub struct DataControlOffer {
+    selection: SelectionType,
+    generation: u64,
+    mime_types: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct DataControlState {
+    devices: Vec<ZwlrDataControlDeviceV1>,
+    /// Data-control sources owning the selections, cancelled once they are replaced.
+    clipboard_source: Option<ZwlrDataControlSourceV1>,
+    primary_source: Option<ZwlrDataControlSourceV1>,
+    /// Number of times the selections changed, to tell outdated offers apart.
+    clipboard_generation: u64,
+    primary_generation: u64,
+}
+
+impl DataControlState {
+    /// Creates the data-control global. Every client can see it: NoWay has no way to tell
+    /// clipboard managers apart from other clients, so like in other compositors without a
+    /// security context, any client may read and set the selections.
+    pub fn new(dh: &DisplayHandle) -> Self {
+        dh.create_global::<NoWayState, ZwlrDataControlManagerV1, ()>(2, ());
+        Self::default()
+    }
+
+    fn generation(&self, selection: SelectionType) -> u64 {
+        match selection {
+            SelectionType::Clipboard => self.clipboard_generation,
+            SelectionType::Primary => self.primary_generation,
+        }
+    }
+}
+
+/// Introduces the selection to a data-control device with a new offer.
+fn send_offer(
+    dh: &DisplayHandle,
+    device: &ZwlrDataControlDeviceV1,
+    selection: SelectionType,
+    generation: u64,
+    mime_types: &[String],
+) {
+    // Primary selection only exists since version 2.
+    if matches!(selection, SelectionType::Primary) && device.version() < 2 {
+        return;
+    }
+
+    let offer = if mime_types.is_empty() {
+        None
+    } else {
+        let Ok(client) = dh.get_client(device.id()) else {
+            return;
+        };
+        let data = DataControlOffer {
+            selection,
+            generation,
+            mime_types: mime_types.to_vec(),
+        };
+        let Ok(offer) = client.create_resource::<ZwlrDataControlOfferV1, _, NoWayState>(
+            dh,
+            device.version(),
+            data,
+        ) else {
+            return;
+        };
+
+        device.data_offer(&offer);
+        for mime_type in mime_types {
+            offer.offer(mime_type.clone());
+        }
+        Some(offer)
+    };
+
+    match selection {
+        SelectionType::Clipboard => device.selection(offer.as_ref()),
+        SelectionType::Primary => device.primary_selection(offer.as_ref()),
+    }
+}
+
+impl NoWayState {
+    /// Tells data-control clients about a new selection, and cancels the data-control source that
+    /// owned the previous one.
+    pub fn offer_selection_to_data_control(&mut self, selection: SelectionType) {
+        let owner = match self.selection_source(selection) {
+            Some(SelectionSource::DataControl(source)) => Some(source),
+            _ => None,
+        };
+        let state = &mut self.data_control_state;
+        let (previous, generation, mime_types) = match selection {
+            SelectionType::Clipboard => (
+                &mut state.clipboard_source,
+                &mut state.clipboard_generation,
+                &self.clipboard_mime_types,
+            ),
+            SelectionType::Primary => (
+                &mut state.primary_source,
+                &mut state.primary_generation,
+                &self.primary_mime_types,
+            ),
+        };
+        *generation += 1;
+        let generation = *generation;
+        if let Some(previous) = previous
+            .take()
+            .filter(|previous| Some(previous) != owner.as_ref())
+        {
+            previous.cancelled();
+        }
+        *previous = owner;
+
+        for device in &self.data_control_state.devices {
+            send_offer(
+                &self.display_handle,
+                device,
+                selection,
+                generation,
+                mime_types,
+            );
+        }
+    }
+
+    /// Sets a selection on behalf of a data-control client, or clears it.
+    fn set_data_control_selection(
+        &mut self,
+        selection: SelectionType,
+        source: Option<ZwlrDataControlSourceV1>,
+    ) {
+        let mime_types = source.as_ref().map(|source| {
+            let data = source.data::<Mutex<DataControlSource>>().unwrap();
+            data.lock().unwrap().mime_types.clone()
+        });
+
+        let (dh, seat) = (&self.display_handle, &self.seat);
+        match (selection, source) {
+            (SelectionType::Clipboard, Some(source)) => {
+                let source = SelectionSource::DataControl(source);
+                set_data_device_selection(dh, seat, mime_types.clone().unwrap_or_default(), source);
+            }
+            (SelectionType::Clipboard, None) => clear_data_device_selection(dh, seat),
+            (SelectionType::Primary, Some(source)) => {
+                let source = SelectionSource::DataControl(source);
+                set_primary_selection(dh, seat, mime_types.clone().unwrap_or_default(), source);
+            }
+            (SelectionType::Primary, None) => clear_primary_selection(dh, seat),
+        }
+
+        let current = match selection {
+            SelectionType::Clipboard => &mut self.clipboard_mime_types,
+            SelectionType::Primary => &mut self.primary_mime_types,
+        };
+        *current = mime_types.clone().unwrap_or_default();
+        tracing::debug!(
+            "New {:?} selection from data-control: {:?}",
+            selection,
+            current
+        );
+
+        self.offer_selection_to_x11(selection, mime_types);
+        self.offer_selection_to_data_control(selection);
+    }
+
+    /// Clears the selections owned by a destroyed data-control source.
+    fn remove_data_control_source(&mut self, id: &ObjectId) {
+        for selection in [SelectionType::Clipboard, SelectionType::Primary] {
+            if let Some(SelectionSource::DataControl(source)) = self.selection_source(selection) {
+                if &source.id() == id {
+                    self.set_data_control_selection(selection, None);
+                }
+            }
+        }
+    }
+}
+
+/// Marks a source as used for a selection, posting an error if it already was.
+fn use_source(device: &ZwlrDataControlDeviceV1, source: &ZwlrDataControlSourceV1) -> bool {
+    let mut data = source
+        .data::<Mutex<DataControlSource>>()
+        .unwrap()
+        .lock()
+        .unwrap();
+    if data.used {
+        device.post_error(
+            zwlr_data_control_device_v1::Error::UsedSource,
+            "the source was already used for a selection",
+        );
+        return false;
+    }
+
+    data.used = true;
+    true
+}
+
+impl GlobalDispatch<ZwlrDataControlManagerV1, ()> for NoWayState {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrDataControlManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for NoWayState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrDataControlManagerV1,
+        request: zwlr_data_control_manager_v1::Request,
+        _data: &(),
+        dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_data_control_manager_v1::Request::CreateDataSource { id } => {
+                data_init.init(id, Mutex::new(DataControlSource::default()));
+            }
+            // NoWay has a single seat.
+            zwlr_data_control_manager_v1::Request::GetDataDevice { id, .. } => {
+                let device = data_init.init(id, ());
+                for (selection, mime_types) in [
+                    (SelectionType::Clipboard, &state.clipboard_mime_types),
+                    (SelectionType::Primary, &state.primary_mime_types),
+                ] {
+                    let generation = state.data_control_state.generation(selection);
+                    send_offer(dhandle, &device, selection, generation, mime_types);
+                }
+                state.data_control_state.devices.push(device);
+            }
+            zwlr_data_control_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for NoWayState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrDataControlDeviceV1,
+        request: zwlr_data_control_device_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let (selection, source) = match request {
+            zwlr_data_control_device_v1::Request::SetSelection { source } => {
+                (SelectionType::Clipboard, source)
+            }
+            zwlr_data_control_device_v1::Request::SetPrimarySelection { source } => {
+                (SelectionType::Primary, source)
+            }
+            zwlr_data_control_device_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        if let Some(source) = &source {
+            if !use_source(resource, source) {
+                return;
+            }
+        }
+        state.set_data_control_selection(selection, source);
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: ObjectId, _data: &()) {
+        let devices = &mut state.data_control_state.devices;
+        devices.retain(|device| device.id() != resource);
+    }
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, Mutex<DataControlSource>> for NoWayState {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrDataControlSourceV1,
+        request: zwlr_data_control_source_v1::Request,
+        data: &Mutex<DataControlSource>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_data_control_source_v1::Request::Offer { mime_type } => {
+                let mut data = data.lock().unwrap();
+                if data.used {
+                    resource.post_error(
+                        zwlr_data_control_source_v1::Error::InvalidOffer,
+                        "offer sent after the source was used for a selection",
+                    );
+                    return;
+                }
+                data.mime_types.push(mime_type);
+            }
+            zwlr_data_control_source_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        resource: ObjectId,
+        _data: &Mutex<DataControlSource>,
+    ) {
+        state.remove_data_control_source(&resource);
+    }
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, DataControlOffer> for NoWayState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrDataControlOfferV1,
+        request: zwlr_data_control_offer_v1::Request,
+        data: &DataControlOffer,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_data_control_offer_v1::Request::Receive { mime_type, fd } => {
+                // Dropping the fd closes it, so the client reads no data.
+                let current = state.data_control_state.generation(data.selection);
+                if data.generation != current || !data.mime_types.contains(&mime_type) {
+                    tracing::debug!(
+                        "Ignoring read of an outdated {:?} offer or unoffered mime type",
+                        data.selection
+                    );
+                    return;
+                }
+                state.request_selection(data.selection, mime_type, fd);
+            }
+            zwlr_data_control_offer_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
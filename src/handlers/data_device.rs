@@ -1,18 +1,106 @@
+use std::os::fd::{AsRawFd, OwnedFd};
+
 use smithay::{
     delegate_data_device,
-    wayland::data_device::{
-        ClientDndGrabHandler, DataDeviceHandler, DataDeviceState, ServerDndGrabHandler,
+    input::Seat,
+    reexports::{
+        wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+        wayland_server::protocol::wl_data_source::WlDataSource,
+    },
+    wayland::{
+        data_device::{
+            current_data_device_selection_userdata, request_data_device_client_selection,
+            with_source_metadata, ClientDndGrabHandler, DataDeviceHandler, DataDeviceState,
+            ServerDndGrabHandler,
+        },
+        primary_selection::{current_primary_selection_userdata, request_primary_client_selection},
     },
+    xwayland::xwm::SelectionType,
 };
 
 use crate::state::NoWayState;
 
+/// Client owning a selection the compositor set on its behalf.
+#[derive(Debug, Clone)]
+pub enum SelectionSource {
+    X11,
+    DataControl(ZwlrDataControlSourceV1),
+}
+
+impl NoWayState {
+    /// Owner of a selection set by the compositor, `None` when a Wayland client set it.
+    pub fn selection_source(&self, selection: SelectionType) -> Option<SelectionSource> {
+        match selection {
+            SelectionType::Clipboard => current_data_device_selection_userdata(&self.seat),
+            SelectionType::Primary => current_primary_selection_userdata(&self.seat),
+        }
+    }
+
+    /// Writes a selection the compositor set on behalf of a client to `fd`.
+    pub fn send_compositor_selection(
+        &mut self,
+        source: &SelectionSource,
+        selection: SelectionType,
+        mime_type: String,
+        fd: OwnedFd,
+    ) {
+        match source {
+            SelectionSource::X11 => self.send_x11_selection(selection, mime_type, fd),
+            SelectionSource::DataControl(source) => source.send(mime_type, fd.as_raw_fd()),
+        }
+    }
+
+    /// Writes the current selection in the given mime type to `fd`, whichever client owns it.
+    pub fn request_selection(&mut self, selection: SelectionType, mime_type: String, fd: OwnedFd) {
+        if let Some(source) = self.selection_source(selection) {
+            self.send_compositor_selection(&source, selection, mime_type, fd);
+            return;
+        }
+
+        let result = match selection {
+            SelectionType::Clipboard => {
+                request_data_device_client_selection(&self.seat, mime_type, fd)
+                    .map_err(|e| e.to_string())
+            }
+            SelectionType::Primary => request_primary_client_selection(&self.seat, mime_type, fd)
+                .map_err(|e| e.to_string()),
+        };
+        if let Err(err) = result {
+            tracing::warn!("Failed to request {:?} selection: {}", selection, err);
+        }
+    }
+}
+
 impl ClientDndGrabHandler for NoWayState {}
 impl ServerDndGrabHandler for NoWayState {}
 impl DataDeviceHandler for NoWayState {
+    type SelectionUserData = SelectionSource;
+
     fn data_device_state(&self) -> &DataDeviceState {
         &self.data_device_state
     }
+
+    fn new_selection(&mut self, source: Option<WlDataSource>, _seat: Seat<Self>) {
+        self.clipboard_mime_types = source
+            .as_ref()
+            .and_then(|source| with_source_metadata(source, |meta| meta.mime_types.clone()).ok())
+            .unwrap_or_default();
+
+        tracing::debug!("New clipboard selection: {:?}", self.clipboard_mime_types);
+        let mime_types = source.map(|_| self.clipboard_mime_types.clone());
+        self.offer_selection_to_x11(SelectionType::Clipboard, mime_types);
+        self.offer_selection_to_data_control(SelectionType::Clipboard);
+    }
+
+    fn send_selection(
+        &mut self,
+        mime_type: String,
+        fd: OwnedFd,
+        _seat: Seat<Self>,
+        source: &SelectionSource,
+    ) {
+        self.send_compositor_selection(source, SelectionType::Clipboard, mime_type, fd);
+    }
 }
 
 delegate_data_device!(NoWayState);
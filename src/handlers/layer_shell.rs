@@ -0,0 +1,226 @@
+use smithay::{
+    delegate_layer_shell,
+    desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType},
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+    wayland::{
+        compositor::with_states,
+        shell::{
+            wlr_layer::{
+                KeyboardInteractivity, Layer, LayerSurface as WlrLayerSurface,
+                LayerSurfaceCachedState, LayerSurfaceData, WlrLayerShellHandler,
+                WlrLayerShellState,
+            },
+            xdg::PopupSurface,
+        },
+    },
+};
+
+use crate::state::NoWayState;
+
+impl NoWayState {
+    /// Finds the topmost layer surface on one of `layers` under `pos`, together with its location
+    /// in global coordinates. A fullscreen window covers all but the overlay layer.
+    pub fn layer_under(
+        &self,
+        pos: Point<f64, Logical>,
+        layers: &[Layer],
+    ) -> Option<(LayerSurface, Point<i32, Logical>)> {
+        let output = self.space.output_under(pos).next()?;
+        let output_geo = self.space.output_geometry(output).unwrap();
+        let fullscreen = self.fullscreen_window(output).is_some();
+        let map = layer_map_for_output(output);
+
+        layers.iter().find_map(|layer| {
+            if fullscreen && *layer != Layer::Overlay {
+                return None;
+            }
+
+            let surface = map.layer_under(*layer, pos - output_geo.loc.to_f64())?;
+            let location = map.layer_geometry(surface).unwrap().loc + output_geo.loc;
+            Some((surface.clone(), location))
+        })
+    }
+
+    /// Finds the layer surface of `surface` and the output it is on.
+    pub fn layer_for_surface(&self, surface: &WlSurface) -> Option<(Output, LayerSurface)> {
+        self.space.outputs().find_map(|output| {
+            let map = layer_map_for_output(output);
+            let layer = map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)?;
+            Some((output.clone(), layer.clone()))
+        })
+    }
+
+    pub fn layer_surface_under(
+        &self,
+        pos: Point<f64, Logical>,
+        layers: &[Layer],
+    ) -> Option<(WlSurface, Point<i32, Logical>)> {
+        let (layer, location) = self.layer_under(pos, layers)?;
+        layer
+            .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
+            .map(|(surface, loc)| (surface, loc + location))
+    }
+
+    /// Layer surface on the top or overlay layer that requested exclusive keyboard
+    /// interactivity. It keeps the keyboard focus until it is unmapped.
+    pub fn exclusive_layer_surface(&self) -> Option<WlSurface> {
+        self.space.outputs().find_map(|output| {
+            let map = layer_map_for_output(output);
+            let layer = map.layers().find(|layer| {
+                matches!(layer.layer(), Layer::Top | Layer::Overlay)
+                    && keyboard_interactivity(layer.wl_surface())
+                        == KeyboardInteractivity::Exclusive
+            })?;
+            Some(layer.wl_surface().clone())
+        })
+    }
+
+    /// Area of the output that is not covered by exclusive zones of layer surfaces, in global
+    /// coordinates.
+    pub fn usable_output_area(&self, output: &Output) -> Rectangle<i32, Logical> {
+        let output_geo = self.space.output_geometry(output).unwrap();
+        let mut area = layer_map_for_output(output).non_exclusive_zone();
+        area.loc += output_geo.loc;
+        area
+    }
+
+    pub fn commit_layer_surface(&mut self, surface: &WlSurface) {
+        let Some(output) = self
+            .space
+            .outputs()
+            .find(|output| {
+                layer_map_for_output(output)
+                    .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+            .cloned()
+        else {
+            return;
+        };
+
+        let initial_configure_sent = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<LayerSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .initial_configure_sent
+        });
+
+        let mut map = layer_map_for_output(&output);
+        let zone = map.non_exclusive_zone();
+        map.arrange();
+        let zone_changed = map.non_exclusive_zone() != zone;
+        if !initial_configure_sent {
+            let layer = map
+                .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                .unwrap();
+            layer.layer_surface().send_configure();
+        }
+        drop(map);
+
+        // Tiled and maximized windows make room for the exclusive zone of new layer surfaces,
+        // and follow when a layer surface changes its exclusive zone, anchor or margin.
+        if !initial_configure_sent || zone_changed {
+            self.arrange_output(&output);
+        }
+
+        if self.exclusive_layer_surface().as_ref() == Some(surface) {
+            let keyboard = self.seat.get_keyboard().unwrap();
+            if keyboard.current_focus().as_ref() != Some(surface) {
+                keyboard.set_focus(self, Some(surface.clone()), SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+}
+
+fn keyboard_interactivity(surface: &WlSurface) -> KeyboardInteractivity {
+    with_states(surface, |states| {
+        states
+            .cached_state
+            .current::<LayerSurfaceCachedState>()
+            .keyboard_interactivity
+    })
+}
+
+/// Whether clicking the layer surface should give it the keyboard focus.
+pub fn can_receive_keyboard_focus(layer: &LayerSurface) -> bool {
+    keyboard_interactivity(layer.wl_surface()) != KeyboardInteractivity::None
+}
+
+impl WlrLayerShellHandler for NoWayState {
+    fn shell_state(&mut self) -> &mut WlrLayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(
+        &mut self,
+        surface: WlrLayerSurface,
+        output: Option<WlOutput>,
+        _layer: Layer,
+        namespace: String,
+    ) {
+        // Surfaces without a preferred output go to the one under the pointer.
+        let output = output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| {
+                self.space
+                    .output_under(self.pointer_location)
+                    .next()
+                    .cloned()
+            })
+            .or_else(|| self.space.outputs().next().cloned());
+        let Some(output) = output else {
+            tracing::warn!("No output for new layer surface `{}`", namespace);
+            surface.send_close();
+            return;
+        };
+
+        let mut map = layer_map_for_output(&output);
+        if let Err(err) = map.map_layer(&LayerSurface::new(surface, namespace)) {
+            tracing::warn!("Failed to map layer surface: {}", err);
+        }
+    }
+
+    fn new_popup(&mut self, _parent: WlrLayerSurface, popup: PopupSurface) {
+        // The popup has no parent yet in `XdgShellHandler::new_popup`, so it is placed here.
+        self.unconstrain_popup(&popup);
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        for output in outputs {
+            let mut map = layer_map_for_output(&output);
+            let layer = map
+                .layers()
+                .find(|layer| layer.layer_surface() == &surface)
+                .cloned();
+            let Some(layer) = layer else {
+                continue;
+            };
+            map.unmap_layer(&layer);
+            map.arrange();
+            drop(map);
+
+            // Windows take back the exclusive zone of the layer surface.
+            self.arrange_output(&output);
+        }
+
+        // Give the keyboard back to the topmost window if the layer surface had it.
+        let keyboard = self.seat.get_keyboard().unwrap();
+        if keyboard.current_focus().as_ref() == Some(surface.wl_surface()) {
+            let focus = self
+                .space
+                .elements()
+                .last()
+                .and_then(|window| window.wl_surface());
+            keyboard.set_focus(self, focus, SERIAL_COUNTER.next_serial());
+        }
+    }
+}
+
+delegate_layer_shell!(NoWayState);
@@ -1,6 +1,14 @@
 pub mod compositor;
+pub mod data_control;
 pub mod data_device;
+pub mod layer_shell;
 pub mod output;
+pub mod pointer_constraints;
+pub mod primary_selection;
+pub mod relative_pointer;
 pub mod seat;
 pub mod shm;
+pub mod touch;
+pub mod xdg_decoration;
 pub mod xdg_shell;
+pub mod xwayland;
@@ -0,0 +1,381 @@
+//! `zwp_pointer_constraints_v1`, which the smithay version NoWay uses does not implement yet.
+
+use smithay::{
+    desktop::utils::bbox_from_surface_tree,
+    reexports::{
+        wayland_protocols::wp::pointer_constraints::zv1::server::{
+            zwp_confined_pointer_v1::{self, ZwpConfinedPointerV1},
+            zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
+            zwp_pointer_constraints_v1::{self, Lifetime, ZwpPointerConstraintsV1},
+        },
+        wayland_server::{
+            backend::{ClientId, ObjectId},
+            protocol::{wl_region::WlRegion, wl_surface::WlSurface},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+        },
+    },
+    utils::{IsAlive, Logical, Point},
+    wayland::compositor::{get_region_attributes, RegionAttributes},
+};
+
+use crate::state::NoWayState;
+
+/// How an active constraint restricts the pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerConstraintKind {
+    /// The pointer does not move, clients only get relative motion.
+    Lock,
+    /// The pointer stays inside the region of the surface.
+    Confine,
+}
+
+#[derive(Debug)]
+enum ConstraintResource {
+    Lock(ZwpLockedPointerV1),
+    Confine(ZwpConfinedPointerV1),
+}
+
+#[derive(Debug)]
+pub struct PointerConstraint {
+    resource: ConstraintResource,
+    surface: WlSurface,
+    /// Part of the surface the constraint applies to, the whole surface if unset.
+    region: Option<RegionAttributes>,
+    /// Region set by the client, applied on the next commit of the surface.
+    pending_region: Option<Option<RegionAttributes>>,
+    /// Where the pointer should be once a lock ends, relative to the surface.
+    cursor_hint: Option<Point<f64, Logical>>,
+    pending_cursor_hint: Option<Point<f64, Logical>>,
+    /// Oneshot constraints are dropped once deactivated, persistent ones may activate again.
+    oneshot: bool,
+    /// Location of the surface while the constraint is active.
+    origin: Option<Point<i32, Logical>>,
+}
+
+impl PointerConstraint {
+    fn kind(&self) -> PointerConstraintKind {
+        match self.resource {
+            ConstraintResource::Lock(_) => PointerConstraintKind::Lock,
+            ConstraintResource::Confine(_) => PointerConstraintKind::Confine,
+        }
+    }
+
+    fn id(&self) -> ObjectId {
+        match &self.resource {
+            ConstraintResource::Lock(locked) => locked.id(),
+            ConstraintResource::Confine(confined) => confined.id(),
+        }
+    }
+
+    /// Whether the surface-local point is in the region of the constraint.
+    fn contains(&self, point: Point<f64, Logical>) -> bool {
+        let point = point.to_i32_floor();
+        bbox_from_surface_tree(&self.surface, (0, 0)).contains(point)
+            && self
+                .region
+                .as_ref()
+                .map_or(true, |region| region.contains(point))
+    }
+
+    fn activate(&mut self, origin: Point<i32, Logical>) {
+        self.origin = Some(origin);
+        match &self.resource {
+            ConstraintResource::Lock(locked) => locked.locked(),
+            ConstraintResource::Confine(confined) => confined.confined(),
+        }
+    }
+
+    fn deactivate(&mut self) {
+        self.origin = None;
+        match &self.resource {
+            ConstraintResource::Lock(locked) => locked.unlocked(),
+            ConstraintResource::Confine(confined) => confined.unconfined(),
+        }
+    }
+
+    /// Moves the pointer from `from` towards `to` as far as the active constraint allows. When
+    /// the target is outside the region, the pointer slides along its edge if possible.
+    fn constrain(&self, from: Point<f64, Logical>, to: Point<f64, Logical>) -> Point<f64, Logical> {
+        let Some(origin) = self.origin else {
+            return to;
+        };
+        if self.kind() == PointerConstraintKind::Lock {
+            return from;
+        }
+
+        let origin = origin.to_f64();
+        [to, (to.x, from.y).into(), (from.x, to.y).into()]
+            .into_iter()
+            .find(|point| self.contains(*point - origin))
+            .unwrap_or(from)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PointerConstraintsState {
+    constraints: Vec<PointerConstraint>,
+}
+
+impl PointerConstraintsState {
+    pub fn new(dh: &DisplayHandle) -> Self {
+        dh.create_global::<NoWayState, ZwpPointerConstraintsV1, ()>(1, ());
+        Self::default()
+    }
+
+    fn active(&self) -> Option<&PointerConstraint> {
+        self.constraints
+            .iter()
+            .find(|constraint| constraint.origin.is_some())
+    }
+
+    fn get_mut(&mut self, id: &ObjectId) -> Option<&mut PointerConstraint> {
+        self.constraints
+            .iter_mut()
+            .find(|constraint| &constraint.id() == id)
+    }
+}
+
+fn region_attributes(region: Option<WlRegion>) -> Option<RegionAttributes> {
+    region.map(|region| get_region_attributes(&region))
+}
+
+impl NoWayState {
+    /// Kind of the constraint currently restricting the pointer.
+    pub fn active_pointer_constraint(&self) -> Option<PointerConstraintKind> {
+        self.pointer_constraints_state
+            .active()
+            .map(PointerConstraint::kind)
+    }
+
+    /// Where the pointer ends up when moving from its location to `location` while the active
+    /// constraint holds.
+    pub fn constrain_pointer(&self, location: Point<f64, Logical>) -> Point<f64, Logical> {
+        match self.pointer_constraints_state.active() {
+            Some(constraint) => constraint.constrain(self.pointer_location, location),
+            None => location,
+        }
+    }
+
+    /// Activates the constraint of the surface under the pointer once the pointer is inside its
+    /// region, and deactivates the constraints of every other surface.
+    pub fn update_pointer_constraints(&mut self, focus: Option<&(WlSurface, Point<i32, Logical>)>) {
+        let location = self.pointer_location;
+        let constraints = &mut self.pointer_constraints_state.constraints;
+        constraints.retain_mut(|constraint| {
+            if !constraint.surface.alive() {
+                return false;
+            }
+
+            let origin = focus
+                .filter(|(surface, _)| *surface == constraint.surface)
+                .map(|(_, origin)| *origin);
+            match (origin, constraint.origin) {
+                (Some(origin), None) if constraint.contains(location - origin.to_f64()) => {
+                    constraint.activate(origin);
+                }
+                (None, Some(_)) => {
+                    constraint.deactivate();
+                    return !constraint.oneshot;
+                }
+                _ => {}
+            }
+            true
+        });
+    }
+
+    /// Re-evaluates the constraints for the surface under the pointer without moving it, e.g.
+    /// after a constraint or its region changed.
+    fn refresh_pointer_constraints(&mut self) {
+        // Like in `move_pointer`, grabs take the pointer away from the constrained surface.
+        let pointer = self.seat.get_pointer().unwrap();
+        let focus = self
+            .surface_under(self.pointer_location)
+            .filter(|_| !pointer.is_grabbed());
+        self.update_pointer_constraints(focus.as_ref());
+    }
+
+    /// Applies the double-buffered state of the constraints of a committed surface.
+    pub fn commit_pointer_constraints(&mut self, surface: &WlSurface) {
+        let constraints = &mut self.pointer_constraints_state.constraints;
+        let mut constrained = false;
+        for constraint in constraints.iter_mut().filter(|c| &c.surface == surface) {
+            constrained = true;
+            if let Some(region) = constraint.pending_region.take() {
+                constraint.region = region;
+            }
+            if let Some(hint) = constraint.pending_cursor_hint.take() {
+                constraint.cursor_hint = Some(hint);
+            }
+        }
+
+        // A new region or a surface that grew may now contain the pointer.
+        if constrained {
+            self.refresh_pointer_constraints();
+        }
+    }
+
+    fn new_pointer_constraint(
+        &mut self,
+        resource: ConstraintResource,
+        surface: WlSurface,
+        region: Option<WlRegion>,
+        lifetime: WEnum<Lifetime>,
+    ) {
+        let constrained = self
+            .pointer_constraints_state
+            .constraints
+            .iter()
+            .any(|constraint| constraint.surface == surface);
+        let constraint = PointerConstraint {
+            resource,
+            surface,
+            region: region_attributes(region),
+            pending_region: None,
+            cursor_hint: None,
+            pending_cursor_hint: None,
+            oneshot: lifetime != WEnum::Value(Lifetime::Persistent),
+            origin: None,
+        };
+
+        if constrained {
+            let message = "the surface already has a pointer constraint";
+            let error = zwp_pointer_constraints_v1::Error::AlreadyConstrained;
+            match &constraint.resource {
+                ConstraintResource::Lock(locked) => locked.post_error(error, message),
+                ConstraintResource::Confine(confined) => confined.post_error(error, message),
+            }
+            return;
+        }
+
+        self.pointer_constraints_state.constraints.push(constraint);
+        // The pointer may already be inside the region, it does not have to move first.
+        self.refresh_pointer_constraints();
+    }
+
+    /// Drops a constraint the client destroyed. Ending a lock moves the pointer to the position
+    /// hinted by the client.
+    fn remove_pointer_constraint(&mut self, id: &ObjectId) {
+        let constraints = &mut self.pointer_constraints_state.constraints;
+        let Some(index) = constraints.iter().position(|c| &c.id() == id) else {
+            return;
+        };
+
+        let constraint = constraints.remove(index);
+        if let (Some(origin), Some(hint)) = (constraint.origin, constraint.cursor_hint) {
+            self.warp_pointer(origin.to_f64() + hint);
+        }
+    }
+}
+
+impl GlobalDispatch<ZwpPointerConstraintsV1, ()> for NoWayState {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpPointerConstraintsV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwpPointerConstraintsV1, ()> for NoWayState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwpPointerConstraintsV1,
+        request: zwp_pointer_constraints_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_pointer_constraints_v1::Request::LockPointer {
+                id,
+                surface,
+                region,
+                lifetime,
+                ..
+            } => {
+                let locked = data_init.init(id, ());
+                let resource = ConstraintResource::Lock(locked);
+                state.new_pointer_constraint(resource, surface, region, lifetime);
+            }
+            zwp_pointer_constraints_v1::Request::ConfinePointer {
+                id,
+                surface,
+                region,
+                lifetime,
+                ..
+            } => {
+                let confined = data_init.init(id, ());
+                let resource = ConstraintResource::Confine(confined);
+                state.new_pointer_constraint(resource, surface, region, lifetime);
+            }
+            zwp_pointer_constraints_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Dispatch<ZwpLockedPointerV1, ()> for NoWayState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwpLockedPointerV1,
+        request: zwp_locked_pointer_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        // Oneshot constraints that ended are gone, requests for them are ignored.
+        let Some(constraint) = state.pointer_constraints_state.get_mut(&resource.id()) else {
+            return;
+        };
+
+        match request {
+            zwp_locked_pointer_v1::Request::SetCursorPositionHint {
+                surface_x,
+                surface_y,
+            } => constraint.pending_cursor_hint = Some((surface_x, surface_y).into()),
+            zwp_locked_pointer_v1::Request::SetRegion { region } => {
+                constraint.pending_region = Some(region_attributes(region));
+            }
+            zwp_locked_pointer_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: ObjectId, _data: &()) {
+        state.remove_pointer_constraint(&resource);
+    }
+}
+
+impl Dispatch<ZwpConfinedPointerV1, ()> for NoWayState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwpConfinedPointerV1,
+        request: zwp_confined_pointer_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let Some(constraint) = state.pointer_constraints_state.get_mut(&resource.id()) else {
+            return;
+        };
+
+        match request {
+            zwp_confined_pointer_v1::Request::SetRegion { region } => {
+                constraint.pending_region = Some(region_attributes(region));
+            }
+            zwp_confined_pointer_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: ObjectId, _data: &()) {
+        state.remove_pointer_constraint(&resource);
+    }
+}
@@ -0,0 +1,45 @@
+use std::os::fd::OwnedFd;
+
+use smithay::{
+    delegate_primary_selection,
+    input::Seat,
+    reexports::wayland_protocols::wp::primary_selection::zv1::server::zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1,
+    wayland::primary_selection::{
+        with_source_metadata, PrimarySelectionHandler, PrimarySelectionState,
+    },
+    xwayland::xwm::SelectionType,
+};
+
+use crate::{handlers::data_device::SelectionSource, state::NoWayState};
+
+impl PrimarySelectionHandler for NoWayState {
+    type SelectionUserData = SelectionSource;
+
+    fn primary_selection_state(&self) -> &PrimarySelectionState {
+        &self.primary_selection_state
+    }
+
+    fn new_selection(&mut self, source: Option<ZwpPrimarySelectionSourceV1>, _seat: Seat<Self>) {
+        self.primary_mime_types = source
+            .as_ref()
+            .and_then(|source| with_source_metadata(source, |meta| meta.mime_types.clone()).ok())
+            .unwrap_or_default();
+
+        tracing::debug!("New primary selection: {:?}", self.primary_mime_types);
+        let mime_types = source.map(|_| self.primary_mime_types.clone());
+        self.offer_selection_to_x11(SelectionType::Primary, mime_types);
+        self.offer_selection_to_data_control(SelectionType::Primary);
+    }
+
+    fn send_selection(
+        &mut self,
+        mime_type: String,
+        fd: OwnedFd,
+        _seat: Seat<Self>,
+        source: &SelectionSource,
+    ) {
+        self.send_compositor_selection(source, SelectionType::Primary, mime_type, fd);
+    }
+}
+
+delegate_primary_selection!(NoWayState);
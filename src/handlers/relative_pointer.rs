@@ -0,0 +1,5 @@
+use smithay::delegate_relative_pointer;
+
+use crate::state::NoWayState;
+
+delegate_relative_pointer!(NoWayState);
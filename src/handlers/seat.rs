@@ -1,57 +1,161 @@
 use smithay::{
     backend::input::{
         AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent,
-        KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+        TouchEvent,
     },
     delegate_seat,
     input::{
         keyboard::FilterResult,
-        pointer::{AxisFrame, ButtonEvent, CursorImageStatus, MotionEvent},
+        pointer::{
+            AxisFrame, ButtonEvent, CursorImageStatus, Focus, GrabStartData, MotionEvent,
+            RelativeMotionEvent,
+        },
         Seat, SeatHandler, SeatState,
     },
-    reexports::wayland_server::protocol::wl_surface::WlSurface,
-    utils::SERIAL_COUNTER,
+    output::Output,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, Resource},
+    utils::{Logical, Point, Serial, SERIAL_COUNTER},
+    wayland::{
+        data_device::set_data_device_focus, primary_selection::set_primary_focus,
+        shell::wlr_layer::Layer,
+    },
+};
+
+use crate::{
+    bindings::{vt_switch, Action},
+    grabs::{
+        move_grab::MoveSurfaceGrab,
+        resize_grab::{ResizeEdge, ResizeSurfaceGrab},
+    },
+    handlers::layer_shell::can_receive_keyboard_focus,
+    render::window::WindowElement,
+    state::NoWayState,
 };
 
-use crate::{render::window::WindowElement, state::NoWayState};
+// The button is a button code as defined in the
+// Linux kernel's linux/input-event-codes.h header file, e.g. BTN_LEFT.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
 
 impl NoWayState {
+    /// Moves the window with the left button, or resizes it with the right button from the
+    /// corner in the quadrant under the pointer.
+    fn start_modifier_drag(&mut self, window: WindowElement, button: u32, serial: Serial) {
+        let pointer = self.seat.get_pointer().unwrap();
+        let Some(geometry) = self.space.element_geometry(&window) else {
+            return;
+        };
+        // The window is the focus of the grab, so the grab ends when the window is hidden.
+        let location = self.space.element_location(&window).unwrap_or(geometry.loc);
+        let start_data = GrabStartData {
+            focus: window.wl_surface().map(|surface| (surface, location)),
+            button,
+            location: pointer.current_location(),
+        };
+
+        if button == BTN_LEFT {
+            let grab = MoveSurfaceGrab {
+                start_data,
+                window,
+                initial_window_location: geometry.loc,
+            };
+            pointer.set_grab(self, grab, serial, Focus::Clear);
+            return;
+        }
+
+        let center = geometry.loc.to_f64() + geometry.size.to_f64().downscale(2.0).to_point();
+        let mut edges = ResizeEdge::empty();
+        edges |= if start_data.location.x < center.x {
+            ResizeEdge::LEFT
+        } else {
+            ResizeEdge::RIGHT
+        };
+        edges |= if start_data.location.y < center.y {
+            ResizeEdge::TOP
+        } else {
+            ResizeEdge::BOTTOM
+        };
+
+        if let Some(grab) = ResizeSurfaceGrab::start(start_data, window, edges, geometry.loc) {
+            pointer.set_grab(self, grab, serial, Focus::Clear);
+        }
+    }
+
     pub fn process_input_event<I: InputBackend>(&mut self, event: InputEvent<I>) {
         match event {
             InputEvent::Keyboard { event, .. } => {
                 let serial = SERIAL_COUNTER.next_serial();
                 let time = Event::time_msec(&event);
+                let keycode = event.key_code();
+                let key_state = event.state();
 
-                self.seat.get_keyboard().unwrap().input::<(), _>(
+                let action = self.seat.get_keyboard().unwrap().input(
                     self,
-                    event.key_code(),
-                    event.state(),
+                    keycode,
+                    key_state,
                     serial,
                     time,
-                    |_, _, _| FilterResult::Forward,
+                    |state, modifiers, handle| {
+                        if key_state == KeyState::Released {
+                            if state.suppressed_keys.contains(&keycode) {
+                                state.suppressed_keys.retain(|key| *key != keycode);
+                                return FilterResult::Intercept(None);
+                            }
+                            return FilterResult::Forward;
+                        }
+
+                        // VT switching cannot be rebound, so the TTYs stay reachable.
+                        if let Some(vt) = vt_switch(&handle) {
+                            state.suppressed_keys.push(keycode);
+                            return FilterResult::Intercept(Some(Action::SwitchVt(vt)));
+                        }
+
+                        let binding = state
+                            .config
+                            .bindings
+                            .iter()
+                            .find(|binding| binding.matches(modifiers, &handle));
+                        match binding {
+                            Some(binding) => {
+                                let action = binding.action.clone();
+                                // The client saw no press, so it must not see the release.
+                                state.suppressed_keys.push(keycode);
+                                FilterResult::Intercept(Some(action))
+                            }
+                            None => FilterResult::Forward,
+                        }
+                    },
                 );
-            }
-            InputEvent::PointerMotion { .. } => {}
-            InputEvent::PointerMotionAbsolute { event, .. } => {
-                let output = self.space.outputs().next().unwrap();
-                let output_geo = self.space.output_geometry(output).unwrap();
-                let pos = event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
 
-                let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.seat.get_pointer().unwrap();
-                let focus = self.surface_under_pointer(&pointer);
+                // Layout switches through `grp:*` xkb options happen in the xkb state.
+                let modifiers = self.seat.get_keyboard().unwrap().modifier_state();
+                self.keyboard_group_changed(modifiers.serialized.layout_locked);
 
-                pointer.motion(
+                if let Some(action) = action.flatten() {
+                    self.run_action(action);
+                }
+            }
+            InputEvent::PointerMotion { event, .. } => {
+                let location = self.pointer_location + event.delta();
+                let focus = self.move_pointer(location, event.time_msec());
+
+                // Clients get relative motion even when the pointer is locked.
+                self.seat.get_pointer().unwrap().relative_motion(
                     self,
                     focus,
-                    &MotionEvent {
-                        location: pos,
-                        serial,
-                        time: event.time_msec(),
+                    &RelativeMotionEvent {
+                        delta: event.delta(),
+                        delta_unaccel: event.delta_unaccel(),
+                        utime: event.time(),
                     },
                 );
-
-                self.pointer_location = pointer.current_location();
+            }
+            InputEvent::PointerMotionAbsolute { event, .. } => {
+                // Without outputs there is nothing the pointer could be on.
+                if let Some(output) = self.space.outputs().next().cloned() {
+                    self.pointer_motion_absolute::<I>(&event, &output);
+                }
             }
             InputEvent::PointerButton { event, .. } => {
                 let pointer = self.seat.get_pointer().unwrap();
@@ -61,9 +165,42 @@ impl NoWayState {
                 let button = event.button_code();
                 let button_state = event.state();
 
+                let mut decoration_hit = None;
+                let mut drag = None;
                 if ButtonState::Pressed == button_state && !pointer.is_grabbed() {
-                    if let Some((window, _)) = self.window_under_pointer(&pointer) {
+                    let pos = pointer.current_location();
+                    let upper_layer = self.layer_under(pos, &[Layer::Overlay, Layer::Top]);
+                    let lower_layer = self
+                        .layer_under(pos, &[Layer::Bottom, Layer::Background])
+                        .filter(|(layer, _)| can_receive_keyboard_focus(layer));
+
+                    if let Some(surface) = self.exclusive_layer_surface() {
+                        // A layer surface with exclusive keyboard interactivity keeps the focus.
+                        keyboard.set_focus(self, Some(surface), serial);
+                    } else if let Some((layer, _)) = upper_layer {
+                        if can_receive_keyboard_focus(&layer) {
+                            keyboard.set_focus(self, Some(layer.wl_surface().clone()), serial);
+                        }
+                    } else if self
+                        .window_under_pointer(&pointer)
+                        .map_or(false, |(window, _)| window.is_override_redirect())
+                    {
+                        // Clicking into an X11 menu leaves the focus on the window it belongs to.
+                    } else if let Some((window, location)) = self.window_under_pointer(&pointer) {
                         let window = window.clone();
+                        let dragging = matches!(button, BTN_LEFT | BTN_RIGHT)
+                            && self
+                                .config
+                                .drag_modifier
+                                .matches(&keyboard.modifier_state());
+                        if dragging {
+                            drag = Some(window.clone());
+                        } else {
+                            decoration_hit = window
+                                .decoration_under(pos - location.to_f64())
+                                .map(|hit| (window.clone(), hit));
+                        }
+
                         self.space.raise_element(&window, true);
                         self.space.elements().for_each(|window| {
                             if let WindowElement::Xdg(window) = window {
@@ -71,7 +208,15 @@ impl NoWayState {
                             }
                         });
 
-                        keyboard.set_focus(self, Some(window.wl_surface().unwrap()), serial);
+                        if let (WindowElement::X11(surface), Some(xwm)) = (&window, &mut self.xwm) {
+                            if let Err(err) = xwm.raise_window(surface) {
+                                tracing::warn!("Failed to raise X11 window: {}", err);
+                            }
+                        }
+
+                        keyboard.set_focus(self, window.wl_surface(), serial);
+                    } else if let Some((layer, _)) = lower_layer {
+                        keyboard.set_focus(self, Some(layer.wl_surface().clone()), serial);
                     } else {
                         self.space.elements().for_each(|window| {
                             if let WindowElement::Xdg(window) = window {
@@ -84,6 +229,11 @@ impl NoWayState {
                     }
                 };
 
+                // The grab starts before the press is delivered, so the client does not see it.
+                if let Some(window) = drag {
+                    self.start_modifier_drag(window, button, serial);
+                }
+
                 pointer.button(
                     self,
                     &ButtonEvent {
@@ -93,6 +243,10 @@ impl NoWayState {
                         time: event.time_msec(),
                     },
                 );
+
+                if let Some((window, hit)) = decoration_hit {
+                    self.decoration_pressed(window, hit, button, serial);
+                }
             }
             InputEvent::PointerAxis { event, .. } => {
                 let source = event.source();
@@ -128,9 +282,112 @@ impl NoWayState {
 
                 self.seat.get_pointer().unwrap().axis(self, frame);
             }
+            InputEvent::TouchDown { event, .. } => {
+                if let Some(location) = self.touch_location(&event) {
+                    self.touch_down(event.slot(), location, event.time_msec());
+                }
+            }
+            InputEvent::TouchMotion { event, .. } => {
+                if let Some(location) = self.touch_location(&event) {
+                    self.touch_motion(event.slot(), location, event.time_msec());
+                }
+            }
+            InputEvent::TouchUp { event, .. } => self.touch_up(event.slot(), event.time_msec()),
+            InputEvent::TouchFrame { .. } => self.touch_frame(),
+            InputEvent::TouchCancel { .. } => self.touch_cancel(),
             _ => {}
         }
     }
+
+    /// Moves the pointer to an absolute position on the given output.
+    pub fn pointer_motion_absolute<I: InputBackend>(
+        &mut self,
+        event: &I::PointerMotionAbsoluteEvent,
+        output: &Output,
+    ) {
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return;
+        };
+        let pos = event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
+        let last = self.absolute_pointer_location.replace(pos);
+        let focus = self.move_pointer(pos, event.time_msec());
+
+        // The winit and X11 backends only report absolute positions, so relative motion is made
+        // up from the distance between them.
+        if let Some(last) = last.filter(|last| *last != pos) {
+            self.seat.get_pointer().unwrap().relative_motion(
+                self,
+                focus,
+                &RelativeMotionEvent {
+                    delta: pos - last,
+                    delta_unaccel: pos - last,
+                    utime: event.time(),
+                },
+            );
+        }
+    }
+
+    /// Moves the pointer to a location chosen by the compositor, e.g. the cursor position hint
+    /// of a pointer lock that ended.
+    pub fn warp_pointer(&mut self, location: Point<f64, Logical>) {
+        let time = self.start_time.elapsed().as_millis() as u32;
+        self.move_pointer(location, time);
+    }
+
+    /// Moves the pointer towards `location` as far as the active pointer constraint allows,
+    /// keeping it on the outputs. Returns the surface under the pointer.
+    fn move_pointer(
+        &mut self,
+        location: Point<f64, Logical>,
+        time: u32,
+    ) -> Option<(WlSurface, Point<i32, Logical>)> {
+        let location = self.clamp_to_outputs(self.constrain_pointer(location));
+        let serial = SERIAL_COUNTER.next_serial();
+        let pointer = self.seat.get_pointer().unwrap();
+        let focus = self.surface_under(location);
+
+        pointer.motion(
+            self,
+            focus.clone(),
+            &MotionEvent {
+                location,
+                serial,
+                time,
+            },
+        );
+        self.pointer_location = pointer.current_location();
+
+        // Grabs such as moving a window take the pointer away from the constrained surface.
+        let constraint_focus = focus.as_ref().filter(|_| !pointer.is_grabbed());
+        self.update_pointer_constraints(constraint_focus);
+
+        focus
+    }
+
+    /// Moves the location onto the nearest output if it is outside of all of them.
+    fn clamp_to_outputs(&self, location: Point<f64, Logical>) -> Point<f64, Logical> {
+        if self.space.output_under(location).next().is_some() {
+            return location;
+        }
+
+        let distance = |point: Point<f64, Logical>| {
+            let delta = point - location;
+            delta.x * delta.x + delta.y * delta.y
+        };
+        self.space
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .map(|geometry| {
+                let (x, y) = (geometry.loc.x as f64, geometry.loc.y as f64);
+                let (w, h) = (geometry.size.w as f64, geometry.size.h as f64);
+                Point::from((
+                    location.x.clamp(x, x + w - 1.0),
+                    location.y.clamp(y, y + h - 1.0),
+                ))
+            })
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+            .unwrap_or(location)
+    }
 }
 
 impl SeatHandler for NoWayState {
@@ -144,6 +401,22 @@ impl SeatHandler for NoWayState {
     fn cursor_image(&mut self, _seat: &Seat<Self>, image: CursorImageStatus) {
         *self.cursor_status.lock().unwrap() = image;
     }
+
+    fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&WlSurface>) {
+        // Selections are offered to the client owning the keyboard focus.
+        let dh = &self.display_handle;
+        let client = focused.and_then(|surface| dh.get_client(surface.id()).ok());
+        set_data_device_focus(dh, seat, client.clone());
+        set_primary_focus(dh, seat, client);
+
+        if let Some(window) = focused.and_then(|surface| self.window_for_surface(surface)) {
+            self.scroll_to_window(&window);
+        }
+
+        // The keymap cannot be changed while the keyboard is busy changing the focus.
+        self.loop_handle
+            .insert_idle(|data| data.state.restore_keyboard_layout());
+    }
 }
 
 delegate_seat!(NoWayState);
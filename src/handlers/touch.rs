@@ -0,0 +1,212 @@
+use smithay::{
+    backend::input::{AbsolutePositionEvent, InputBackend, TouchSlot},
+    output::Output,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, Resource},
+    utils::{IsAlive, Logical, Point, Serial, SERIAL_COUNTER},
+    wayland::shell::wlr_layer::Layer,
+};
+
+use crate::{
+    grabs::{resize_grab::ResizeEdge, touch_grab::TouchGrab},
+    handlers::layer_shell::can_receive_keyboard_focus,
+    render::{decoration::DecorationHit, window::WindowElement},
+    state::NoWayState,
+};
+
+/// A finger currently on the touchscreen.
+#[derive(Debug)]
+pub struct TouchPoint {
+    slot: TouchSlot,
+    /// Serial of the down event, clients pass it to start a move or resize.
+    serial: Serial,
+    location: Point<f64, Logical>,
+    focus: Option<WlSurface>,
+}
+
+impl NoWayState {
+    /// Output touch devices are mapped to: the configured one, or the first output.
+    pub fn touch_output(&self) -> Option<Output> {
+        let configured = self
+            .config
+            .touch_output
+            .as_ref()
+            .and_then(|name| self.space.outputs().find(|output| &output.name() == name));
+        configured.or_else(|| self.space.outputs().next()).cloned()
+    }
+
+    /// Location of a touch event on the output touch devices are mapped to.
+    pub fn touch_location<I: InputBackend>(
+        &self,
+        event: &impl AbsolutePositionEvent<I>,
+    ) -> Option<Point<f64, Logical>> {
+        let output = self.touch_output()?;
+        let geometry = self.space.output_geometry(&output)?;
+        Some(event.position_transformed(geometry.size) + geometry.loc.to_f64())
+    }
+
+    pub fn touch_down(&mut self, slot: TouchSlot, location: Point<f64, Logical>, time: u32) {
+        // While a window follows one finger, the others are ignored.
+        if self.touch_grab.is_some() {
+            return;
+        }
+
+        let serial = SERIAL_COUNTER.next_serial();
+        let focus = self.surface_under(location);
+        self.touch_points.retain(|point| point.slot != slot);
+        self.touch_points.push(TouchPoint {
+            slot,
+            serial,
+            location,
+            focus: focus.as_ref().map(|(surface, _)| surface.clone()),
+        });
+
+        let window = self
+            .window_under(location)
+            .filter(|(window, _)| !window.is_override_redirect())
+            .map(|(window, window_location)| (window.clone(), window_location));
+        // The focus follows the rules of pointer buttons: a touch on a layer surface above the
+        // windows focuses no window, and a layer surface with exclusive keyboard interactivity
+        // keeps the focus.
+        let keyboard = self.seat.get_keyboard().unwrap();
+        if let Some(surface) = self.exclusive_layer_surface() {
+            keyboard.set_focus(self, Some(surface), serial);
+        } else if let Some((layer, _)) = self.layer_under(location, &[Layer::Overlay, Layer::Top]) {
+            if can_receive_keyboard_focus(&layer) {
+                keyboard.set_focus(self, Some(layer.wl_surface().clone()), serial);
+            }
+        } else if let Some((window, window_location)) = window {
+            self.focus_window(&window);
+            if let Some(hit) = window.decoration_under(location - window_location.to_f64()) {
+                self.decoration_touched(window, hit, slot, location);
+                return;
+            }
+        }
+
+        if let Some((surface, surface_location)) = focus {
+            let touch = self.seat.get_touch().unwrap();
+            touch.down(
+                serial,
+                time,
+                &surface,
+                surface_location.to_f64(),
+                slot,
+                location,
+            );
+        }
+    }
+
+    pub fn touch_motion(&mut self, slot: TouchSlot, location: Point<f64, Logical>, time: u32) {
+        if let Some(point) = self
+            .touch_points
+            .iter_mut()
+            .find(|point| point.slot == slot)
+        {
+            point.location = location;
+        }
+
+        if let Some(mut grab) = self.touch_grab.take() {
+            if grab.slot == slot {
+                if !grab.window().alive() {
+                    return;
+                }
+                grab.motion(self, location);
+            }
+            self.touch_grab = Some(grab);
+            return;
+        }
+
+        self.seat.get_touch().unwrap().motion(time, slot, location);
+    }
+
+    pub fn touch_up(&mut self, slot: TouchSlot, time: u32) {
+        self.touch_points.retain(|point| point.slot != slot);
+
+        let serial = SERIAL_COUNTER.next_serial();
+        match self.touch_grab.take() {
+            Some(mut grab) if grab.slot == slot => grab.up(self, serial),
+            grab => self.touch_grab = grab,
+        }
+
+        // Clients that started a move or resize still get the up of their touch point.
+        self.seat.get_touch().unwrap().up(serial, time, slot);
+    }
+
+    pub fn touch_frame(&mut self) {
+        self.seat.get_touch().unwrap().frame();
+    }
+
+    pub fn touch_cancel(&mut self) {
+        self.touch_points.clear();
+        if let Some(mut grab) = self.touch_grab.take() {
+            grab.up(self, SERIAL_COUNTER.next_serial());
+        }
+
+        self.seat.get_touch().unwrap().cancel();
+    }
+
+    /// Finds the touch point a client wants to move or resize its window with, like
+    /// `check_grab` does for the pointer.
+    pub fn check_touch_grab(
+        &self,
+        surface: &WlSurface,
+        serial: Serial,
+    ) -> Option<(TouchSlot, Point<f64, Logical>)> {
+        let point = self
+            .touch_points
+            .iter()
+            .find(|point| point.serial == serial)?;
+        let focus = point.focus.as_ref()?;
+        if !focus.id().same_client_as(&surface.id()) {
+            return None;
+        }
+
+        Some((point.slot, point.location))
+    }
+
+    pub fn start_touch_move(
+        &mut self,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        window: WindowElement,
+    ) {
+        let Some(initial_window_location) = self.space.element_location(&window) else {
+            return;
+        };
+        self.touch_grab = Some(TouchGrab::start_move(
+            slot,
+            location,
+            window,
+            initial_window_location,
+        ));
+    }
+
+    pub fn start_touch_resize(
+        &mut self,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        window: WindowElement,
+        edges: ResizeEdge,
+    ) {
+        let Some(initial_window_location) = self.space.element_location(&window) else {
+            return;
+        };
+        self.touch_grab =
+            TouchGrab::start_resize(slot, location, window, edges, initial_window_location);
+    }
+
+    /// Touch counterpart of `decoration_pressed`.
+    fn decoration_touched(
+        &mut self,
+        window: WindowElement,
+        hit: DecorationHit,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+    ) {
+        match hit {
+            DecorationHit::TitleBar => self.start_touch_move(slot, location, window),
+            DecorationHit::Border(edges) => self.start_touch_resize(slot, location, window, edges),
+            DecorationHit::Close => window.send_close(),
+            DecorationHit::Maximize => self.toggle_maximize(&window),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use smithay::{
+    delegate_xdg_decoration,
+    input::pointer::{Focus, GrabStartData},
+    reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+    utils::Serial,
+    wayland::shell::xdg::{decoration::XdgDecorationHandler, ToplevelSurface},
+};
+
+use crate::{
+    grabs::{move_grab::MoveSurfaceGrab, resize_grab::ResizeSurfaceGrab},
+    handlers::xdg_shell::initial_configure_sent,
+    render::{decoration::DecorationHit, window::WindowElement},
+    state::NoWayState,
+};
+
+// The button is a button code as defined in the
+// Linux kernel's linux/input-event-codes.h header file, e.g. BTN_LEFT.
+const BTN_LEFT: u32 = 0x110;
+
+impl NoWayState {
+    fn preferred_decoration_mode(&self) -> DecorationMode {
+        if self.config.server_side_decorations {
+            DecorationMode::ServerSide
+        } else {
+            DecorationMode::ClientSide
+        }
+    }
+
+    fn set_decoration_mode(&self, toplevel: &ToplevelSurface, mode: DecorationMode) {
+        toplevel.with_pending_state(|state| {
+            state.decoration_mode = Some(mode);
+        });
+
+        // Before the initial configure the mode is sent along with it.
+        if initial_configure_sent(toplevel) {
+            toplevel.send_configure();
+        }
+    }
+}
+
+impl NoWayState {
+    /// Handles a button press on the server-side decorations of `window`.
+    pub fn decoration_pressed(
+        &mut self,
+        window: WindowElement,
+        hit: DecorationHit,
+        button: u32,
+        serial: Serial,
+    ) {
+        if button != BTN_LEFT {
+            return;
+        }
+
+        let pointer = self.seat.get_pointer().unwrap();
+        let initial_window_location = self.space.element_location(&window).unwrap();
+        // The window is the focus of the grab, so the grab ends when the window is hidden.
+        let start_data = GrabStartData {
+            focus: window
+                .wl_surface()
+                .map(|surface| (surface, initial_window_location)),
+            button,
+            location: pointer.current_location(),
+        };
+
+        match hit {
+            DecorationHit::TitleBar => {
+                let grab = MoveSurfaceGrab {
+                    start_data,
+                    window,
+                    initial_window_location,
+                };
+                pointer.set_grab(self, grab, serial, Focus::Clear);
+            }
+            DecorationHit::Border(edges) => {
+                let Some(grab) =
+                    ResizeSurfaceGrab::start(start_data, window, edges, initial_window_location)
+                else {
+                    return;
+                };
+                pointer.set_grab(self, grab, serial, Focus::Clear);
+            }
+            DecorationHit::Close => window.send_close(),
+            DecorationHit::Maximize => self.toggle_maximize(&window),
+        }
+    }
+}
+
+impl XdgDecorationHandler for NoWayState {
+    fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        self.set_decoration_mode(&toplevel, self.preferred_decoration_mode());
+    }
+
+    fn request_mode(&mut self, toplevel: ToplevelSurface, mode: DecorationMode) {
+        self.set_decoration_mode(&toplevel, mode);
+    }
+
+    fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        self.set_decoration_mode(&toplevel, self.preferred_decoration_mode());
+    }
+}
+
+delegate_xdg_decoration!(NoWayState);
@@ -2,28 +2,34 @@ use std::cell::RefCell;
 
 use smithay::{
     delegate_xdg_shell,
-    desktop::{space::SpaceElement, Window},
+    desktop::{
+        find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output,
+        space::SpaceElement, PopupKeyboardGrab, PopupKind, PopupPointerGrab, PopupUngrabStrategy,
+        Window,
+    },
     input::{
         pointer::{Focus, GrabStartData},
         Seat,
     },
+    output::Output,
     reexports::{
         wayland_protocols::xdg::shell::server::xdg_toplevel,
         wayland_server::{
             protocol::{
+                wl_output::WlOutput,
                 wl_seat::{self, WlSeat},
                 wl_surface::WlSurface,
             },
             Resource,
         },
     },
-    utils::Serial,
+    utils::{Logical, Point, Rectangle, Serial},
     wayland::{
         compositor::{with_states, with_surface_tree_upward, TraversalAction},
         seat::WaylandFocus,
         shell::xdg::{
-            PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
-            XdgToplevelSurfaceData,
+            PopupSurface, PositionerState, ToplevelSurface, XdgPopupSurfaceData, XdgShellHandler,
+            XdgShellState, XdgToplevelSurfaceData,
         },
     },
 };
@@ -31,13 +37,27 @@ use smithay::{
 use crate::{
     grabs::{
         move_grab::MoveSurfaceGrab,
-        resize_grab::{ResizeData, ResizeState, ResizeSurfaceGrab},
+        resize_grab::{is_resizing, ResizeData, ResizeState, ResizeSurfaceGrab},
         SurfaceData,
     },
-    render::window::WindowElement,
+    render::{decoration, window::WindowElement},
     state::NoWayState,
 };
 
+/// Whether the toplevel got its initial configure. Configures sent before it would not be
+/// expected by the client.
+pub fn initial_configure_sent(toplevel: &ToplevelSurface) -> bool {
+    with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .initial_configure_sent
+    })
+}
+
 impl NoWayState {
     pub fn commit_xdg_surface(&self, surface: &WlSurface) {
         with_surface_tree_upward(
@@ -52,11 +72,20 @@ impl NoWayState {
             |_, _, _| true,
         );
 
-        if let Some(WindowElement::Xdg(window)) = self.window_for_surface(surface) {
+        let window = self
+            .window_for_surface(surface)
+            .or_else(|| self.pending_window_for_surface(surface));
+        if let Some(WindowElement::Xdg(window)) = window {
+            if !initial_configure_sent(window.toplevel()) {
+                window.toplevel().send_configure();
+            }
+        }
+
+        if let Some(PopupKind::Xdg(popup)) = self.popups.find_popup(surface) {
             let initial_configure_sent = with_states(surface, |states| {
                 states
                     .data_map
-                    .get::<XdgToplevelSurfaceData>()
+                    .get::<XdgPopupSurfaceData>()
                     .unwrap()
                     .lock()
                     .unwrap()
@@ -64,7 +93,9 @@ impl NoWayState {
             });
 
             if !initial_configure_sent {
-                window.toplevel().send_configure();
+                if let Err(err) = popup.send_configure() {
+                    tracing::warn!("Failed to send initial popup configure: {}", err);
+                }
             }
         }
 
@@ -80,6 +111,213 @@ impl NoWayState {
         });
     }
 
+    /// Usable area of the output for a maximized window's geometry, leaving room for its
+    /// decorations.
+    pub fn maximized_area(
+        &self,
+        window: &WindowElement,
+        output: &Output,
+    ) -> Rectangle<i32, Logical> {
+        let area = self.usable_output_area(output);
+        if window.has_server_side_decorations() {
+            decoration::content_area(area)
+        } else {
+            area
+        }
+    }
+
+    /// Remembers the geometry of a mapped window before it is maximized or made fullscreen. The
+    /// geometry from before the first of both is kept.
+    fn save_geometry(&self, window: &WindowElement) {
+        let (Some(surface), Some(location)) =
+            (window.wl_surface(), self.space.element_location(window))
+        else {
+            return;
+        };
+
+        let current = Rectangle::from_loc_and_size(location, window.geometry().size);
+        with_states(&surface, |states| {
+            states
+                .data_map
+                .insert_if_missing(|| RefCell::new(SurfaceData::default()));
+            let mut data = states
+                .data_map
+                .get::<RefCell<SurfaceData>>()
+                .unwrap()
+                .borrow_mut();
+            data.geometry.get_or_insert(current);
+        });
+    }
+
+    fn take_saved_geometry(&self, window: &WindowElement) -> Option<Rectangle<i32, Logical>> {
+        with_states(&window.wl_surface()?, |states| {
+            states
+                .data_map
+                .get::<RefCell<SurfaceData>>()?
+                .borrow_mut()
+                .geometry
+                .take()
+        })
+    }
+
+    /// Sends the pending state of the window and, if it is mapped already, moves it to
+    /// `location` and rearranges its output. Windows not mapped yet get the state with their
+    /// initial configure and are placed accordingly.
+    fn apply_window_state(
+        &mut self,
+        window: &WindowElement,
+        location: Option<Point<i32, Logical>>,
+    ) {
+        let WindowElement::Xdg(xdg) = window else {
+            return;
+        };
+        if initial_configure_sent(xdg.toplevel()) {
+            xdg.toplevel().send_configure();
+        }
+
+        if self.space.element_location(window).is_none() {
+            return;
+        }
+        if let Some(location) = location {
+            self.space.map_element(window.clone(), location, true);
+        }
+        if let Some(output) = self.output_for_window(window) {
+            self.arrange_output(&output);
+        }
+    }
+
+    /// Maximizes the window to the usable area of its output.
+    pub fn maximize(&mut self, window: &WindowElement) {
+        let WindowElement::Xdg(xdg) = window else {
+            return;
+        };
+        if window.is_maximized() {
+            return;
+        }
+        let Some(output) = self
+            .output_for_window(window)
+            .or_else(|| self.focused_output())
+        else {
+            return;
+        };
+
+        self.save_geometry(window);
+        let area = self.maximized_area(window, &output);
+        // A fullscreen window keeps covering the output until it leaves fullscreen.
+        let fullscreen = window.is_fullscreen();
+        xdg.toplevel().with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Maximized);
+            if !fullscreen {
+                state.size = Some(area.size);
+            }
+        });
+        self.apply_window_state(window, (!fullscreen).then_some(area.loc));
+    }
+
+    /// Restores the geometry the window had before being maximized.
+    pub fn unmaximize(&mut self, window: &WindowElement) {
+        let WindowElement::Xdg(xdg) = window else {
+            return;
+        };
+        if !window.is_maximized() {
+            return;
+        }
+
+        let fullscreen = window.is_fullscreen();
+        let restore = if fullscreen {
+            None
+        } else {
+            self.take_saved_geometry(window)
+        };
+        xdg.toplevel().with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Maximized);
+            if !fullscreen {
+                state.size = restore.map(|geometry| geometry.size);
+            }
+        });
+        self.apply_window_state(window, restore.map(|geometry| geometry.loc));
+    }
+
+    pub fn toggle_maximize(&mut self, window: &WindowElement) {
+        if window.is_maximized() {
+            self.unmaximize(window);
+        } else {
+            self.maximize(window);
+        }
+    }
+
+    /// Makes the window cover the whole output, by default the one it is shown on.
+    pub fn fullscreen(&mut self, window: &WindowElement, output: Option<Output>) {
+        let WindowElement::Xdg(xdg) = window else {
+            return;
+        };
+        if window.is_fullscreen() {
+            return;
+        }
+        // A client may still hold the `wl_output` of an output that is gone.
+        let Some(output_geo) = output
+            .into_iter()
+            .chain(self.output_for_window(window))
+            .chain(self.focused_output())
+            .find_map(|output| self.space.output_geometry(&output))
+        else {
+            return;
+        };
+
+        self.save_geometry(window);
+        xdg.toplevel().with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Fullscreen);
+            state.size = Some(output_geo.size);
+        });
+        self.apply_window_state(window, Some(output_geo.loc));
+    }
+
+    /// Returns the window to the maximized state or the geometry it had before.
+    pub fn unfullscreen(&mut self, window: &WindowElement) {
+        let WindowElement::Xdg(xdg) = window else {
+            return;
+        };
+        if !window.is_fullscreen() {
+            return;
+        }
+
+        xdg.toplevel().with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Fullscreen);
+        });
+        let restore = if window.is_maximized() {
+            self.output_for_window(window)
+                .map(|output| self.maximized_area(window, &output))
+        } else {
+            self.take_saved_geometry(window)
+        };
+        xdg.toplevel().with_pending_state(|state| {
+            state.size = restore.map(|geometry| geometry.size);
+        });
+        self.apply_window_state(window, restore.map(|geometry| geometry.loc));
+    }
+
+    pub fn toggle_fullscreen(&mut self, window: &WindowElement) {
+        if window.is_fullscreen() {
+            self.unfullscreen(window);
+        } else {
+            self.fullscreen(window, None);
+        }
+    }
+
+    /// Topmost fullscreen window shown on the output. It is drawn above everything but the
+    /// overlay layer.
+    pub fn fullscreen_window(&self, output: &Output) -> Option<&WindowElement> {
+        self.space.elements().rev().find(|window| {
+            window.is_fullscreen() && self.output_for_window(window).as_ref() == Some(output)
+        })
+    }
+
+    /// Mapped or pending window of the toplevel.
+    fn toplevel_window(&self, surface: &ToplevelSurface) -> Option<WindowElement> {
+        self.window_for_surface(surface.wl_surface())
+            .or_else(|| self.pending_window_for_surface(surface.wl_surface()))
+    }
+
     fn check_grab(
         &self,
         surface: &WlSurface,
@@ -100,6 +338,60 @@ impl NoWayState {
 
         Some(start_data)
     }
+
+    /// Adjusts the popup geometry according to its positioner, so it stays within the outputs its
+    /// toplevel is shown on. Flipping, sliding and resizing are handled by the positioner's
+    /// constraint adjustments. Popups of layer surfaces stay within the output of the layer
+    /// surface.
+    pub fn unconstrain_popup(&self, popup: &PopupSurface) {
+        let kind = PopupKind::Xdg(popup.clone());
+        let Ok(root) = find_popup_root_surface(&kind) else {
+            return;
+        };
+        let Some((area, parent_location)) = self
+            .window_popup_area(&root)
+            .or_else(|| self.layer_popup_area(&root))
+        else {
+            return;
+        };
+
+        // The target geometry for the positioner should be relative to its parent's geometry.
+        let mut target = area;
+        target.loc -= get_popup_toplevel_coords(&kind);
+        target.loc -= parent_location;
+
+        popup.with_pending_state(|state| {
+            state.geometry = state.positioner.get_unconstrained_geometry(target);
+        });
+    }
+
+    /// Union of the geometries of the outputs the window of `root` is on, and the location of
+    /// the window geometry.
+    fn window_popup_area(
+        &self,
+        root: &WlSurface,
+    ) -> Option<(Rectangle<i32, Logical>, Point<i32, Logical>)> {
+        let window = self.window_for_surface(root)?;
+        let outputs_geo = self
+            .space
+            .outputs_for_element(&window)
+            .iter()
+            .filter_map(|output| self.space.output_geometry(output))
+            .reduce(|a, b| a.merge(b))?;
+        let window_geo = self.space.element_geometry(&window)?;
+        Some((outputs_geo, window_geo.loc))
+    }
+
+    /// Geometry of the output of the layer surface `root`, and the location of the layer surface.
+    fn layer_popup_area(
+        &self,
+        root: &WlSurface,
+    ) -> Option<(Rectangle<i32, Logical>, Point<i32, Logical>)> {
+        let (output, layer) = self.layer_for_surface(root)?;
+        let output_geo = self.space.output_geometry(&output)?;
+        let layer_geo = layer_map_for_output(&output).layer_geometry(&layer)?;
+        Some((output_geo, output_geo.loc + layer_geo.loc))
+    }
 }
 
 impl XdgShellHandler for NoWayState {
@@ -108,8 +400,62 @@ impl XdgShellHandler for NoWayState {
     }
 
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        // The window is mapped once it committed a buffer, see `map_pending_window`.
         let window = WindowElement::Xdg(Window::new(surface));
-        self.space.map_element(window, (0, 0), false);
+        self.pending_windows.push(window);
+    }
+
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        self.pending_windows
+            .retain(|window| window.wl_surface().as_ref() != Some(surface.wl_surface()));
+        if let Some(window) = self
+            .workspaces
+            .hidden_window_for_surface(surface.wl_surface())
+            .cloned()
+        {
+            self.workspaces.remove_hidden(&window);
+        }
+
+        // Close the gap the window leaves in the layout.
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            let output = self.output_for_window(&window);
+            self.space.unmap_elem(&window);
+            if let Some(output) = output {
+                self.arrange_output(&output);
+            }
+        }
+    }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.toplevel_window(&surface) {
+            self.maximize(&window);
+        }
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.toplevel_window(&surface) {
+            self.unmaximize(&window);
+        }
+    }
+
+    fn fullscreen_request(&mut self, surface: ToplevelSurface, output: Option<WlOutput>) {
+        if let Some(window) = self.toplevel_window(&surface) {
+            let output = output.as_ref().and_then(Output::from_resource);
+            self.fullscreen(&window, output);
+        }
+    }
+
+    fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.toplevel_window(&surface) {
+            self.unfullscreen(&window);
+        }
+    }
+
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        // Only mapped windows can be hidden, xdg-shell has no minimized state to configure.
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            self.minimize_window(&window);
+        }
     }
 
     fn move_request(&mut self, surface: ToplevelSurface, seat: WlSeat, serial: Serial) {
@@ -132,6 +478,9 @@ impl XdgShellHandler for NoWayState {
                 serial,
                 Focus::Clear,
             );
+        } else if let Some((slot, location)) = self.check_touch_grab(surface, serial) {
+            let window = self.window_for_surface(surface).unwrap();
+            self.start_touch_move(slot, location, window);
         }
     }
 
@@ -143,7 +492,12 @@ impl XdgShellHandler for NoWayState {
         edges: xdg_toplevel::ResizeEdge,
     ) {
         let seat: Seat<Self> = Seat::from_resource(&seat).unwrap();
-        // TODO: touch resize.
+        if let Some((slot, location)) = self.check_touch_grab(surface.wl_surface(), serial) {
+            let window = self.window_for_surface(surface.wl_surface()).unwrap();
+            self.start_touch_resize(slot, location, window, edges.into());
+            return;
+        }
+
         let pointer = seat.get_pointer().unwrap();
 
         // Check that this surface has a click grab.
@@ -155,6 +509,11 @@ impl XdgShellHandler for NoWayState {
 
         let window = self.window_for_surface(surface.wl_surface()).unwrap();
 
+        // A touch point may be resizing the window already.
+        if is_resizing(surface.wl_surface()) {
+            return;
+        }
+
         // If the focus was for a different surface, ignore the request.
         if start_data.focus.is_none()
             || !start_data
@@ -196,12 +555,69 @@ impl XdgShellHandler for NoWayState {
         pointer.set_grab(self, grab, serial, Focus::Clear);
     }
 
-    fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {
-        todo!()
+    fn new_popup(&mut self, surface: PopupSurface, positioner: PositionerState) {
+        surface.with_pending_state(|state| {
+            state.geometry = positioner.get_geometry();
+        });
+        self.unconstrain_popup(&surface);
+
+        if let Err(err) = self.popups.track_popup(PopupKind::Xdg(surface)) {
+            tracing::warn!("Failed to track popup: {}", err);
+        }
+    }
+
+    fn reposition_request(
+        &mut self,
+        surface: PopupSurface,
+        positioner: PositionerState,
+        token: u32,
+    ) {
+        surface.with_pending_state(|state| {
+            state.geometry = positioner.get_geometry();
+            state.positioner = positioner;
+        });
+        self.unconstrain_popup(&surface);
+        surface.send_repositioned(token);
     }
 
-    fn grab(&mut self, _surface: PopupSurface, _seat: WlSeat, _serial: Serial) {
-        todo!()
+    fn grab(&mut self, surface: PopupSurface, seat: WlSeat, serial: Serial) {
+        let seat: Seat<Self> = Seat::from_resource(&seat).unwrap();
+        let kind = PopupKind::Xdg(surface);
+
+        let Some(root) = find_popup_root_surface(&kind).ok().filter(|root| {
+            self.window_for_surface(root).is_some() || self.layer_for_surface(root).is_some()
+        }) else {
+            return;
+        };
+
+        let Ok(mut grab) = self.popups.grab_popup(root, kind, &seat, serial) else {
+            return;
+        };
+
+        if let Some(keyboard) = seat.get_keyboard() {
+            if keyboard.is_grabbed()
+                && !(keyboard.has_grab(serial)
+                    || keyboard.has_grab(grab.previous_serial().unwrap_or(serial)))
+            {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+
+            keyboard.set_focus(self, grab.current_grab(), serial);
+            keyboard.set_grab(PopupKeyboardGrab::new(&grab), serial);
+        }
+
+        if let Some(pointer) = seat.get_pointer() {
+            if pointer.is_grabbed()
+                && !(pointer.has_grab(serial)
+                    || pointer.has_grab(grab.previous_serial().unwrap_or_else(|| grab.serial())))
+            {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+
+            pointer.set_grab(self, PopupPointerGrab::new(&grab), serial, Focus::Keep);
+        }
     }
 }
 
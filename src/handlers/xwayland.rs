@@ -0,0 +1,370 @@
+use std::{ffi::OsString, os::fd::OwnedFd};
+
+use smithay::{
+    input::pointer::Focus,
+    utils::{Logical, Point, Rectangle, Size, SERIAL_COUNTER},
+    wayland::{
+        data_device::{clear_data_device_selection, set_data_device_selection},
+        primary_selection::{clear_primary_selection, set_primary_selection},
+    },
+    xwayland::{
+        xwm::{Reorder, ResizeEdge as X11ResizeEdge, SelectionType, X11Wm, XwmHandler, XwmId},
+        X11Surface, XWayland, XWaylandEvent,
+    },
+};
+
+use crate::{
+    grabs::{move_grab::MoveSurfaceGrab, resize_grab::ResizeSurfaceGrab},
+    handlers::data_device::SelectionSource,
+    render::{
+        pointer::{default_cursor_pixels, DEFAULT_CURSOR_SIZE},
+        window::WindowElement,
+    },
+    state::{NoWayData, NoWayState},
+};
+
+impl NoWayState {
+    /// Spawns Xwayland. The X11 window manager is attached once the X server is ready.
+    pub fn start_xwayland(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (xwayland, channel) = XWayland::new(&self.display_handle);
+
+        self.loop_handle
+            .insert_source(channel, move |event, _, data| match event {
+                XWaylandEvent::Ready {
+                    connection,
+                    client,
+                    display,
+                    ..
+                } => {
+                    let wm = X11Wm::start_wm(
+                        data.state.loop_handle.clone(),
+                        data.state.display_handle.clone(),
+                        connection,
+                        client,
+                    );
+
+                    let mut wm = match wm {
+                        Ok(wm) => wm,
+                        Err(err) => {
+                            tracing::error!("Failed to attach X11 window manager: {}", err);
+                            return;
+                        }
+                    };
+
+                    let cursor_size = DEFAULT_CURSOR_SIZE as u16;
+                    if let Err(err) = wm.set_cursor(
+                        &default_cursor_pixels(),
+                        Size::from((cursor_size, cursor_size)),
+                        Point::from((0, 0)),
+                    ) {
+                        tracing::warn!("Failed to set default Xwayland cursor: {}", err);
+                    }
+
+                    tracing::info!("Xwayland is ready on DISPLAY :{}", display);
+                    std::env::set_var("DISPLAY", format!(":{}", display));
+                    data.state.xwm = Some(wm);
+                    data.state.xdisplay = Some(display);
+                    data.state.spawn_startup_command();
+                }
+                XWaylandEvent::Exited => {
+                    tracing::warn!("Xwayland exited");
+                    data.state.xwm = None;
+                    data.state.xdisplay = None;
+                    data.state.remove_x11_windows();
+                    // Xwayland may exit before it was ready, the command still runs without it.
+                    data.state.spawn_startup_command();
+                }
+            })?;
+
+        xwayland.start(
+            self.loop_handle.clone(),
+            None,
+            std::iter::empty::<(OsString, OsString)>(),
+            true,
+            |_| {},
+        )?;
+        self.xwayland = Some(xwayland);
+
+        Ok(())
+    }
+
+    /// Forgets the windows of X11 clients, which are gone once Xwayland exited.
+    fn remove_x11_windows(&mut self) {
+        let windows = self
+            .space
+            .elements()
+            .chain(self.workspaces.hidden_windows())
+            .filter(|window| window.is_x11())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut outputs = Vec::new();
+        for window in windows {
+            self.workspaces.remove_hidden(&window);
+            if let Some(output) = self.output_for_window(&window) {
+                if !outputs.contains(&output) {
+                    outputs.push(output);
+                }
+            }
+            self.space.unmap_elem(&window);
+        }
+
+        for output in outputs {
+            self.arrange_output(&output);
+        }
+    }
+
+    /// Offers a selection of a Wayland client to X11 clients, or clears it there.
+    pub fn offer_selection_to_x11(
+        &mut self,
+        selection: SelectionType,
+        mime_types: Option<Vec<String>>,
+    ) {
+        let Some(xwm) = self.xwm.as_mut() else {
+            return;
+        };
+        if let Err(err) = xwm.new_selection(selection, mime_types) {
+            tracing::warn!("Failed to offer {:?} selection to X11: {}", selection, err);
+        }
+    }
+
+    /// Writes the selection owned by an X11 client to a Wayland client.
+    pub fn send_x11_selection(&mut self, selection: SelectionType, mime_type: String, fd: OwnedFd) {
+        let Some(xwm) = self.xwm.as_mut() else {
+            return;
+        };
+        if let Err(err) = xwm.send_selection(selection, mime_type, fd, self.loop_handle.clone()) {
+            tracing::warn!("Failed to send {:?} selection from X11: {}", selection, err);
+        }
+    }
+
+    pub fn window_for_x11_surface(&self, surface: &X11Surface) -> Option<WindowElement> {
+        self.space
+            .elements()
+            .find(|window| matches!(window, WindowElement::X11(w) if w == surface))
+            .cloned()
+    }
+}
+
+impl XwmHandler for NoWayData {
+    fn xwm_state(&mut self, _xwm: XwmId) -> &mut X11Wm {
+        self.state.xwm.as_mut().unwrap()
+    }
+
+    fn new_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+    fn new_override_redirect_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn map_window_request(&mut self, _xwm: XwmId, window: X11Surface) {
+        if let Err(err) = window.set_mapped(true) {
+            tracing::warn!("Failed to map X11 window: {}", err);
+            return;
+        }
+
+        let element = WindowElement::X11(window.clone());
+        let location = self.state.place_window(&element);
+        self.state.init_window_layout(&element);
+        self.state
+            .space
+            .map_element(element.clone(), location, true);
+
+        let bbox = self.state.space.element_bbox(&element).unwrap();
+        if let Err(err) = window.configure(bbox) {
+            tracing::warn!("Failed to configure X11 window: {}", err);
+        }
+
+        if let Some(output) = self.state.output_for_window(&element) {
+            self.state.arrange_output(&output);
+        }
+    }
+
+    fn mapped_override_redirect_window(&mut self, _xwm: XwmId, window: X11Surface) {
+        // Override-redirect windows (menus, tooltips) place themselves and are never focused.
+        let location = window.geometry().loc;
+        self.state
+            .space
+            .map_element(WindowElement::X11(window), location, false);
+    }
+
+    fn unmapped_window(&mut self, _xwm: XwmId, window: X11Surface) {
+        self.state
+            .workspaces
+            .remove_hidden(&WindowElement::X11(window.clone()));
+        if let Some(element) = self.state.window_for_x11_surface(&window) {
+            let output = self.state.output_for_window(&element);
+            self.state.space.unmap_elem(&element);
+            if let Some(output) = output {
+                self.state.arrange_output(&output);
+            }
+        }
+
+        if !window.is_override_redirect() {
+            if let Err(err) = window.set_mapped(false) {
+                tracing::warn!("Failed to unmap X11 window: {}", err);
+            }
+        }
+    }
+
+    fn destroyed_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn configure_request(
+        &mut self,
+        _xwm: XwmId,
+        window: X11Surface,
+        _x: Option<i32>,
+        _y: Option<i32>,
+        w: Option<u32>,
+        h: Option<u32>,
+        _reorder: Option<Reorder>,
+    ) {
+        // Windows may pick their size, but the compositor is in charge of placing them. Windows
+        // the layout or their state sizes are told to keep their current geometry instead.
+        let mut geometry = window.geometry();
+        let managed = self
+            .state
+            .window_for_x11_surface(&window)
+            .map_or(false, |element| {
+                self.state.is_tiled(&element) || element.is_maximized() || element.is_fullscreen()
+            });
+        if managed {
+            if let Err(err) = window.configure(geometry) {
+                tracing::warn!("Failed to configure X11 window: {}", err);
+            }
+            return;
+        }
+
+        if let Some(w) = w {
+            geometry.size.w = w as i32;
+        }
+        if let Some(h) = h {
+            geometry.size.h = h as i32;
+        }
+
+        if let Err(err) = window.configure(geometry) {
+            tracing::warn!("Failed to configure X11 window: {}", err);
+        }
+    }
+
+    fn configure_notify(
+        &mut self,
+        _xwm: XwmId,
+        window: X11Surface,
+        geometry: Rectangle<i32, Logical>,
+        _above: Option<u32>,
+    ) {
+        let Some(element) = self.state.window_for_x11_surface(&window) else {
+            return;
+        };
+
+        self.state.space.map_element(element, geometry.loc, false);
+    }
+
+    fn resize_request(
+        &mut self,
+        _xwm: XwmId,
+        window: X11Surface,
+        _button: u32,
+        edges: X11ResizeEdge,
+    ) {
+        let pointer = self.state.seat.get_pointer().unwrap();
+        let Some(start_data) = pointer.grab_start_data() else {
+            return;
+        };
+        let Some(element) = self.state.window_for_x11_surface(&window) else {
+            return;
+        };
+
+        let initial_window_location = self.state.space.element_location(&element).unwrap();
+        let Some(grab) =
+            ResizeSurfaceGrab::start(start_data, element, edges.into(), initial_window_location)
+        else {
+            return;
+        };
+
+        pointer.set_grab(
+            &mut self.state,
+            grab,
+            SERIAL_COUNTER.next_serial(),
+            Focus::Clear,
+        );
+    }
+
+    fn allow_selection_access(&mut self, _xwm: XwmId, _selection: SelectionType) -> bool {
+        // Like Wayland clients, X11 clients only get to the selections while they are focused.
+        matches!(self.state.focused_window(), Some(WindowElement::X11(_)))
+    }
+
+    fn send_selection(
+        &mut self,
+        _xwm: XwmId,
+        selection: SelectionType,
+        mime_type: String,
+        fd: OwnedFd,
+    ) {
+        self.state.request_selection(selection, mime_type, fd);
+    }
+
+    fn new_selection(&mut self, _xwm: XwmId, selection: SelectionType, mime_types: Vec<String>) {
+        let state = &mut self.state;
+        tracing::debug!("New {:?} selection from X11: {:?}", selection, mime_types);
+        let (dh, seat) = (&state.display_handle, &state.seat);
+        match selection {
+            SelectionType::Clipboard => {
+                set_data_device_selection(dh, seat, mime_types.clone(), SelectionSource::X11);
+                state.clipboard_mime_types = mime_types;
+            }
+            SelectionType::Primary => {
+                set_primary_selection(dh, seat, mime_types.clone(), SelectionSource::X11);
+                state.primary_mime_types = mime_types;
+            }
+        }
+        state.offer_selection_to_data_control(selection);
+    }
+
+    fn cleared_selection(&mut self, _xwm: XwmId, selection: SelectionType) {
+        // Only clear selections the X11 client owned, another client may have taken over.
+        let state = &mut self.state;
+        if !matches!(
+            state.selection_source(selection),
+            Some(SelectionSource::X11)
+        ) {
+            return;
+        }
+
+        let (dh, seat) = (&state.display_handle, &state.seat);
+        match selection {
+            SelectionType::Clipboard => {
+                clear_data_device_selection(dh, seat);
+                state.clipboard_mime_types.clear();
+            }
+            SelectionType::Primary => {
+                clear_primary_selection(dh, seat);
+                state.primary_mime_types.clear();
+            }
+        }
+        state.offer_selection_to_data_control(selection);
+    }
+
+    fn move_request(&mut self, _xwm: XwmId, window: X11Surface, _button: u32) {
+        let pointer = self.state.seat.get_pointer().unwrap();
+        let Some(start_data) = pointer.grab_start_data() else {
+            return;
+        };
+        let Some(element) = self.state.window_for_x11_surface(&window) else {
+            return;
+        };
+
+        let initial_window_location = self.state.space.element_location(&element).unwrap();
+        let grab = MoveSurfaceGrab {
+            start_data,
+            window: element,
+            initial_window_location,
+        };
+
+        pointer.set_grab(
+            &mut self.state,
+            grab,
+            SERIAL_COUNTER.next_serial(),
+            Focus::Clear,
+        );
+    }
+}
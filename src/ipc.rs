@@ -0,0 +1,293 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+
+use smithay::{
+    backend::input::TouchSlot,
+    reexports::calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction},
+    utils::{Logical, Point},
+};
+
+use crate::{
+    keyboard::layout_name,
+    state::{NoWayData, NoWayState},
+};
+
+/// Longest request accepted from a client, to keep a misbehaving client from growing the buffer.
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// Path of the IPC socket: `$XDG_RUNTIME_DIR/noway-<wayland socket>.sock`.
+pub fn socket_path(socket_name: &str) -> Option<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")?;
+    Some(PathBuf::from(runtime_dir).join(format!("noway-{}.sock", socket_name)))
+}
+
+/// Listens for IPC clients. Every client sends one request line and gets the response lines
+/// back before the connection is closed. The socket path is exported as `NOWAY_SOCKET`.
+pub fn init_ipc(
+    handle: &LoopHandle<'static, NoWayData>,
+    socket_name: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = socket_path(socket_name).ok_or("XDG_RUNTIME_DIR is not set")?;
+    // A socket left behind by a previous instance on the same Wayland socket.
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path)?;
+    listener.set_nonblocking(true)?;
+
+    let client_handle = handle.clone();
+    handle.insert_source(
+        Generic::new(listener, Interest::READ, Mode::Level),
+        move |_, listener, _| {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(err) = add_client(&client_handle, stream) {
+                            tracing::warn!("Failed to accept IPC client: {}", err);
+                        }
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        tracing::warn!("Failed to accept IPC client: {}", err);
+                        break;
+                    }
+                }
+            }
+
+            Ok(PostAction::Continue)
+        },
+    )?;
+
+    std::env::set_var("NOWAY_SOCKET", &path);
+    Ok(path)
+}
+
+fn add_client(
+    handle: &LoopHandle<'static, NoWayData>,
+    stream: UnixStream,
+) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_nonblocking(true)?;
+
+    let mut buffer = Vec::new();
+    handle.insert_source(
+        Generic::new(stream, Interest::READ, Mode::Level),
+        move |_, stream, data| {
+            let mut chunk = [0; 512];
+            loop {
+                match stream.read(&mut chunk) {
+                    // The client closed its side without finishing the request.
+                    Ok(0) => return Ok(PostAction::Remove),
+                    Ok(len) => buffer.extend_from_slice(&chunk[..len]),
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => return Ok(PostAction::Remove),
+                }
+            }
+
+            let Some(end) = buffer.iter().position(|byte| *byte == b'\n') else {
+                if buffer.len() > MAX_REQUEST_LEN {
+                    return Ok(PostAction::Remove);
+                }
+                return Ok(PostAction::Continue);
+            };
+
+            let request = String::from_utf8_lossy(&buffer[..end]).into_owned();
+            if request.trim() == "subscribe" {
+                // Subscribers stay connected and get a line for every event.
+                let _ = stream.write_all(b"ok\n");
+                match stream.try_clone() {
+                    Ok(subscriber) => data.state.ipc_subscribers.push(subscriber),
+                    Err(err) => tracing::warn!("Failed to add IPC subscriber: {}", err),
+                }
+                return Ok(PostAction::Remove);
+            }
+
+            let response = match data.state.handle_ipc_request(request.trim()) {
+                Ok(lines) => lines
+                    .into_iter()
+                    .map(|line| line + "\n")
+                    .collect::<String>(),
+                Err(err) => format!("error: {}\n", err),
+            };
+
+            // Responses are small, a blocking write is fine.
+            let _ = stream.set_nonblocking(false);
+            let _ = stream.write_all(response.as_bytes());
+            Ok(PostAction::Remove)
+        },
+    )?;
+
+    Ok(())
+}
+
+fn parse_workspace(argument: Option<&str>) -> Result<usize, String> {
+    argument
+        .and_then(|argument| argument.parse().ok())
+        .filter(|workspace| (1..=crate::workspace::WORKSPACE_COUNT).contains(workspace))
+        .ok_or_else(|| {
+            format!(
+                "expected a workspace between 1 and {}",
+                crate::workspace::WORKSPACE_COUNT
+            )
+        })
+}
+
+fn parse_touch_slot(argument: Option<&str>) -> Result<TouchSlot, String> {
+    argument
+        .and_then(|argument| argument.parse::<u32>().ok())
+        .map(|slot| TouchSlot::from(Some(slot)))
+        .ok_or_else(|| "expected a touch slot number".to_string())
+}
+
+fn parse_touch_location<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+) -> Result<Point<f64, Logical>, String> {
+    let mut coordinate = || words.next().and_then(|value| value.parse::<f64>().ok());
+    match (coordinate(), coordinate()) {
+        (Some(x), Some(y)) => Ok((x, y).into()),
+        _ => Err("expected a location like `100 200`".to_string()),
+    }
+}
+
+impl NoWayState {
+    /// Feeds a synthetic touch event, e.g. to try touch input on backends without touchscreens.
+    /// Locations are relative to the output touch devices are mapped to.
+    fn synthetic_touch<'a>(
+        &mut self,
+        mut words: impl Iterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        let time = self.start_time.elapsed().as_millis() as u32;
+        let output = self.touch_output().ok_or("no output")?;
+        let origin = self
+            .space
+            .output_geometry(&output)
+            .ok_or("no output")?
+            .loc
+            .to_f64();
+
+        match words.next() {
+            Some("down") => {
+                let slot = parse_touch_slot(words.next())?;
+                let location = parse_touch_location(words)?;
+                self.touch_down(slot, origin + location, time);
+            }
+            Some("motion") => {
+                let slot = parse_touch_slot(words.next())?;
+                let location = parse_touch_location(words)?;
+                self.touch_motion(slot, origin + location, time);
+            }
+            Some("up") => self.touch_up(parse_touch_slot(words.next())?, time),
+            Some("frame") => self.touch_frame(),
+            Some("cancel") => self.touch_cancel(),
+            _ => return Err("expected `down`, `motion`, `up`, `frame` or `cancel`".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Sends an event line to the subscribed IPC clients. Clients that went away or do not read
+    /// their events are dropped.
+    pub fn broadcast_ipc_event(&mut self, event: &str) {
+        let line = format!("{}\n", event);
+        self.ipc_subscribers
+            .retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+    }
+
+    /// Runs an IPC request and returns the lines to answer with.
+    pub fn handle_ipc_request(&mut self, request: &str) -> Result<Vec<String>, String> {
+        let mut words = request.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        if command == "touch" {
+            self.synthetic_touch(words)?;
+            return Ok(vec!["ok".to_string()]);
+        }
+        let argument = words.next();
+
+        match command {
+            "workspaces" => Ok(self.describe_workspaces()),
+            "workspace" => {
+                let workspace = parse_workspace(argument)?;
+                let output = self.focused_output().ok_or("no output")?;
+                if !self.switch_workspace(&output, workspace) {
+                    return Err(format!(
+                        "workspace {} is shown on another output",
+                        workspace
+                    ));
+                }
+                Ok(vec!["ok".to_string()])
+            }
+            "move-to-workspace" => {
+                let workspace = parse_workspace(argument)?;
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.move_window_to_workspace(&window, workspace);
+                Ok(vec!["ok".to_string()])
+            }
+            "layout" => {
+                let layout = argument
+                    .and_then(|argument| argument.parse().ok())
+                    .ok_or("expected `floating`, `master_stack`, `dwindle` or `scrolling`")?;
+                let output = self.focused_output().ok_or("no output")?;
+                self.set_workspace_layout(&output, layout);
+                Ok(vec!["ok".to_string()])
+            }
+            "toggle-floating" => {
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.toggle_floating(&window);
+                Ok(vec!["ok".to_string()])
+            }
+            "toggle-maximize" => {
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.toggle_maximize(&window);
+                Ok(vec!["ok".to_string()])
+            }
+            "toggle-fullscreen" => {
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.toggle_fullscreen(&window);
+                Ok(vec!["ok".to_string()])
+            }
+            "minimize" => {
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.minimize_window(&window);
+                Ok(vec!["ok".to_string()])
+            }
+            "unminimize" => {
+                let output = self.focused_output().ok_or("no output")?;
+                if !self.unminimize_window(&output) {
+                    return Err("no minimized window".to_string());
+                }
+                Ok(vec!["ok".to_string()])
+            }
+            "focus-column" => {
+                let direction = match argument {
+                    Some("left") => -1,
+                    Some("right") => 1,
+                    _ => return Err("expected `left` or `right`".to_string()),
+                };
+                self.focus_column(direction);
+                Ok(vec!["ok".to_string()])
+            }
+            "consume-into-column" => {
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.consume_into_column(&window);
+                Ok(vec!["ok".to_string()])
+            }
+            "expel-from-column" => {
+                let window = self.focused_window().ok_or("no focused window")?;
+                self.expel_from_column(&window);
+                Ok(vec!["ok".to_string()])
+            }
+            "keyboard-layout" => Ok(vec![layout_name(
+                &self.config.keyboard,
+                self.keyboard_layout,
+            )]),
+            "switch-layout" => {
+                self.switch_keyboard_layout();
+                Ok(vec!["ok".to_string()])
+            }
+            "clipboard" => Ok(self.clipboard_mime_types.clone()),
+            "primary-selection" => Ok(self.primary_mime_types.clone()),
+            "" => Err("empty request".to_string()),
+            command => Err(format!("unknown request `{}`", command)),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::cell::Cell;
+
+use smithay::input::keyboard::XkbConfig;
+
+use crate::{config::KeyboardConfig, render::window::WindowElement, state::NoWayState};
+
+/// Keyboard layout last used in a window, kept in its user data.
+struct WindowKeyboardLayout(Cell<usize>);
+
+/// Layouts and variants of a keymap holding every configured layout as an xkb layout group,
+/// starting with `first`. They are comma-joined like for `setxkbmap`.
+pub fn keymap_layouts(config: &KeyboardConfig, first: usize) -> (String, String) {
+    let count = config.layouts.len();
+    let order = (0..count).map(|index| (first + index) % count);
+    let layouts = order
+        .clone()
+        .map(|layout| config.layouts[layout].as_str())
+        .collect::<Vec<_>>();
+    let variants = order
+        .map(|layout| config.variants.get(layout).map_or("", String::as_str))
+        .collect::<Vec<_>>();
+    (layouts.join(","), variants.join(","))
+}
+
+/// Xkb config of a keymap with the given layouts and variants, see [`keymap_layouts`]. Without
+/// configured layouts the xkb defaults are used.
+pub fn xkb_config<'a>(config: &'a KeyboardConfig, layouts: &'a (String, String)) -> XkbConfig<'a> {
+    XkbConfig {
+        rules: &config.rules,
+        model: &config.model,
+        layout: &layouts.0,
+        variant: &layouts.1,
+        options: config.options.clone(),
+    }
+}
+
+/// Name of a configured layout as reported over IPC, e.g. `us` or `de(nodeadkeys)`.
+pub fn layout_name(config: &KeyboardConfig, layout: usize) -> String {
+    let Some(name) = config.layouts.get(layout) else {
+        return "default".to_string();
+    };
+
+    match config
+        .variants
+        .get(layout)
+        .filter(|variant| !variant.is_empty())
+    {
+        Some(variant) => format!("{}({})", name, variant),
+        None => name.clone(),
+    }
+}
+
+fn window_layout(window: &WindowElement, current: usize) -> &Cell<usize> {
+    window
+        .user_data()
+        .insert_if_missing(|| WindowKeyboardLayout(Cell::new(current)));
+    &window.user_data().get::<WindowKeyboardLayout>().unwrap().0
+}
+
+impl NoWayState {
+    /// Loads a keymap with all configured layouts, starting with `layout`. Switching layouts with
+    /// a `grp:*` xkb option changes the layout group of the keymap and keeps held modifiers. The
+    /// smithay version NoWay uses does not let the compositor change the group itself, so
+    /// switching from NoWay reloads the keymap with the layouts rotated instead, which releases
+    /// held modifiers.
+    pub fn set_keyboard_layout(&mut self, layout: usize) {
+        let config = self.config.keyboard.clone();
+        let layouts = keymap_layouts(&config, layout);
+        let keyboard = self.seat.get_keyboard().unwrap();
+        if let Err(err) = keyboard.set_xkb_config(self, xkb_config(&config, &layouts)) {
+            tracing::warn!("Failed to load keyboard layout: {:?}", err);
+            return;
+        }
+
+        self.keymap_first_layout = layout;
+        self.keyboard_layout_switched(layout);
+    }
+
+    /// Picks up a layout switch xkb did on its own, given the locked layout group.
+    pub fn keyboard_group_changed(&mut self, group: u32) {
+        let count = self.config.keyboard.layouts.len().max(1);
+        let layout = (self.keymap_first_layout + group as usize) % count;
+        if layout != self.keyboard_layout {
+            self.keyboard_layout_switched(layout);
+        }
+    }
+
+    fn keyboard_layout_switched(&mut self, layout: usize) {
+        self.keyboard_layout = layout;
+        if let Some(window) = self.focused_window() {
+            window_layout(&window, layout).set(layout);
+        }
+
+        let name = layout_name(&self.config.keyboard, layout);
+        tracing::info!("Switched keyboard layout to {}", name);
+        self.broadcast_ipc_event(&format!("keyboard-layout {}", name));
+    }
+
+    /// Switches to the next configured layout.
+    pub fn switch_keyboard_layout(&mut self) {
+        let count = self.config.keyboard.layouts.len();
+        if count > 1 {
+            self.set_keyboard_layout((self.keyboard_layout + 1) % count);
+        }
+    }
+
+    /// Switches to the layout last used in the focused window. Windows focused for the first
+    /// time keep the current layout.
+    pub fn restore_keyboard_layout(&mut self) {
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+
+        let layout = window_layout(&window, self.keyboard_layout).get();
+        if layout != self.keyboard_layout {
+            self.set_keyboard_layout(layout);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use smithay::utils::{Logical, Rectangle};
+
+use super::Layout;
+
+/// Every window takes half of the remaining area, splitting alternately side by side and on top
+/// of each other.
+#[derive(Debug)]
+pub struct Dwindle;
+
+impl Layout for Dwindle {
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+        let mut rects = Vec::with_capacity(count);
+        let mut remaining = area;
+
+        for index in 0..count {
+            if index + 1 == count {
+                rects.push(remaining);
+                break;
+            }
+
+            let (first, rest) = if index % 2 == 0 {
+                let width = remaining.size.w / 2;
+                (
+                    Rectangle::from_loc_and_size(remaining.loc, (width, remaining.size.h)),
+                    Rectangle::from_loc_and_size(
+                        (remaining.loc.x + width, remaining.loc.y),
+                        (remaining.size.w - width, remaining.size.h),
+                    ),
+                )
+            } else {
+                let height = remaining.size.h / 2;
+                (
+                    Rectangle::from_loc_and_size(remaining.loc, (remaining.size.w, height)),
+                    Rectangle::from_loc_and_size(
+                        (remaining.loc.x, remaining.loc.y + height),
+                        (remaining.size.w, remaining.size.h - height),
+                    ),
+                )
+            };
+
+            rects.push(first);
+            remaining = rest;
+        }
+
+        rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn no_windows_get_no_tiles() {
+        assert!(Dwindle.arrange(rect(0, 0, 1000, 600), 0).is_empty());
+    }
+
+    #[test]
+    fn single_window_takes_the_area() {
+        let area = rect(10, 20, 1000, 600);
+        assert_eq!(Dwindle.arrange(area, 1), vec![area]);
+    }
+
+    #[test]
+    fn splits_alternate_between_columns_and_rows() {
+        assert_eq!(
+            Dwindle.arrange(rect(10, 20, 1000, 600), 4),
+            vec![
+                rect(10, 20, 500, 600),
+                rect(510, 20, 500, 300),
+                rect(510, 320, 250, 300),
+                rect(760, 320, 250, 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn rounding_remainder_goes_to_the_later_tile() {
+        assert_eq!(
+            Dwindle.arrange(rect(0, 0, 1001, 601), 3),
+            vec![
+                rect(0, 0, 500, 601),
+                rect(500, 0, 501, 300),
+                rect(500, 300, 501, 301),
+            ]
+        );
+    }
+
+    #[test]
+    fn tiny_areas_give_empty_tiles_instead_of_negative_ones() {
+        let tiles = Dwindle.arrange(rect(0, 0, 1, 1), 3);
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles
+            .iter()
+            .all(|tile| tile.size.w >= 0 && tile.size.h >= 0));
+        assert_eq!(tiles[0], rect(0, 0, 0, 1));
+    }
+}
@@ -0,0 +1,91 @@
+use smithay::utils::{Logical, Rectangle};
+
+use super::Layout;
+
+/// The first window takes the left part of the area, the others are stacked on the right.
+#[derive(Debug)]
+pub struct MasterStack {
+    /// Share of the area width taken by the master window.
+    pub master_ratio: f64,
+}
+
+impl Layout for MasterStack {
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+        if count <= 1 {
+            return vec![area; count];
+        }
+
+        let master_width = (area.size.w as f64 * self.master_ratio).round() as i32;
+        let mut rects = vec![Rectangle::from_loc_and_size(
+            area.loc,
+            (master_width, area.size.h),
+        )];
+
+        let stack_count = (count - 1) as i32;
+        let stack_x = area.loc.x + master_width;
+        let stack_width = area.size.w - master_width;
+        for index in 0..stack_count {
+            // The last window takes the rounding remainder.
+            let y = area.loc.y + area.size.h * index / stack_count;
+            let next_y = area.loc.y + area.size.h * (index + 1) / stack_count;
+            rects.push(Rectangle::from_loc_and_size(
+                (stack_x, y),
+                (stack_width, next_y - y),
+            ));
+        }
+
+        rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    const LAYOUT: MasterStack = MasterStack { master_ratio: 0.55 };
+
+    #[test]
+    fn no_windows_get_no_tiles() {
+        assert!(LAYOUT.arrange(rect(0, 0, 1000, 600), 0).is_empty());
+    }
+
+    #[test]
+    fn single_window_takes_the_area() {
+        let area = rect(10, 20, 1000, 600);
+        assert_eq!(LAYOUT.arrange(area, 1), vec![area]);
+    }
+
+    #[test]
+    fn master_and_one_stacked_window() {
+        assert_eq!(
+            LAYOUT.arrange(rect(10, 20, 1000, 600), 2),
+            vec![rect(10, 20, 550, 600), rect(560, 20, 450, 600)]
+        );
+    }
+
+    #[test]
+    fn stacked_windows_share_the_height_without_gaps() {
+        let tiles = LAYOUT.arrange(rect(0, 0, 1001, 601), 4);
+        assert_eq!(
+            tiles,
+            vec![
+                rect(0, 0, 551, 601),
+                rect(551, 0, 450, 200),
+                rect(551, 200, 450, 200),
+                rect(551, 400, 450, 201),
+            ]
+        );
+    }
+
+    #[test]
+    fn more_windows_than_pixels_give_empty_tiles() {
+        let tiles = LAYOUT.arrange(rect(0, 0, 100, 2), 4);
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(tiles.iter().skip(1).map(|tile| tile.size.h).sum::<i32>(), 2);
+        assert!(tiles.iter().all(|tile| tile.size.h >= 0));
+    }
+}
@@ -0,0 +1,319 @@
+use std::{cell::Cell, str::FromStr};
+
+use smithay::{
+    desktop::space::SpaceElement,
+    output::Output,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{Logical, Rectangle},
+    wayland::{compositor::with_states, shell::xdg::SurfaceCachedState},
+};
+
+use crate::{
+    config::LayoutConfig,
+    handlers::xdg_shell::initial_configure_sent,
+    render::{decoration, window::WindowElement},
+    state::NoWayState,
+};
+
+use self::{dwindle::Dwindle, master_stack::MasterStack};
+
+pub mod dwindle;
+pub mod master_stack;
+pub mod scrolling;
+
+/// Arranges the tiled windows of a workspace.
+pub trait Layout {
+    /// Splits `area` into `count` rectangles, one per tiled window, in tiling order.
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>>;
+}
+
+/// Layout of a workspace.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LayoutKind {
+    /// Windows are not arranged by the compositor.
+    #[default]
+    Floating,
+    MasterStack,
+    Dwindle,
+    /// Windows open in new columns of a strip that scrolls horizontally.
+    Scrolling,
+}
+
+impl LayoutKind {
+    pub fn layout(self, config: &LayoutConfig) -> Option<Box<dyn Layout>> {
+        match self {
+            // The scrolling layout keeps state, see `Strip`.
+            Self::Floating | Self::Scrolling => None,
+            Self::MasterStack => Some(Box::new(MasterStack {
+                master_ratio: config.master_ratio,
+            })),
+            Self::Dwindle => Some(Box::new(Dwindle)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Floating => "floating",
+            Self::MasterStack => "master_stack",
+            Self::Dwindle => "dwindle",
+            Self::Scrolling => "scrolling",
+        }
+    }
+}
+
+impl FromStr for LayoutKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "floating" => Ok(Self::Floating),
+            "master_stack" => Ok(Self::MasterStack),
+            "dwindle" => Ok(Self::Dwindle),
+            "scrolling" => Ok(Self::Scrolling),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Layout state of a window, kept in its user data.
+#[derive(Debug)]
+struct WindowLayoutData {
+    floating: Cell<bool>,
+    /// Windows are tiled in the order they were mapped, unless they were swapped.
+    order: Cell<u64>,
+}
+
+fn layout_data(window: &WindowElement) -> &WindowLayoutData {
+    window.user_data().insert_if_missing(|| WindowLayoutData {
+        floating: Cell::new(false),
+        order: Cell::new(0),
+    });
+    window.user_data().get::<WindowLayoutData>().unwrap()
+}
+
+pub fn is_floating(window: &WindowElement) -> bool {
+    layout_data(window).floating.get()
+}
+
+/// Whether the window cannot be resized, so tiling it makes no sense.
+fn has_fixed_size(window: &WindowElement) -> bool {
+    let Some(surface) = window.wl_surface() else {
+        return false;
+    };
+
+    with_states(&surface, |states| {
+        let data = states.cached_state.current::<SurfaceCachedState>();
+        data.min_size.w > 0 && data.min_size == data.max_size
+    })
+}
+
+fn set_tiled(window: &WindowElement, geometry: Rectangle<i32, Logical>) {
+    match window {
+        WindowElement::Xdg(xdg) => {
+            xdg.toplevel().with_pending_state(|state| {
+                state.states.set(xdg_toplevel::State::TiledLeft);
+                state.states.set(xdg_toplevel::State::TiledRight);
+                state.states.set(xdg_toplevel::State::TiledTop);
+                state.states.set(xdg_toplevel::State::TiledBottom);
+                state.size = Some(geometry.size);
+            });
+            xdg.toplevel().send_configure();
+        }
+        WindowElement::X11(x11) => {
+            if let Err(err) = x11.configure(geometry) {
+                tracing::warn!("Failed to configure X11 window: {}", err);
+            }
+        }
+    }
+}
+
+/// Geometry of a window filling the tile, leaving room for the gap and its decorations.
+fn tile_geometry(
+    window: &WindowElement,
+    tile: Rectangle<i32, Logical>,
+    half_gap: i32,
+) -> Rectangle<i32, Logical> {
+    let tile = Rectangle::from_loc_and_size(
+        tile.loc + (half_gap, half_gap).into(),
+        (
+            (tile.size.w - 2 * half_gap).max(1),
+            (tile.size.h - 2 * half_gap).max(1),
+        ),
+    );
+    if window.has_server_side_decorations() {
+        decoration::content_area(tile)
+    } else {
+        tile
+    }
+}
+
+fn unset_tiled(window: &WindowElement) {
+    if let WindowElement::Xdg(xdg) = window {
+        let tiled = xdg
+            .toplevel()
+            .with_pending_state(|state| state.states.contains(xdg_toplevel::State::TiledLeft));
+        if !tiled {
+            return;
+        }
+
+        xdg.toplevel().with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::TiledLeft);
+            state.states.unset(xdg_toplevel::State::TiledRight);
+            state.states.unset(xdg_toplevel::State::TiledTop);
+            state.states.unset(xdg_toplevel::State::TiledBottom);
+        });
+        xdg.toplevel().send_configure();
+    }
+}
+
+impl NoWayState {
+    /// Decides whether a newly mapped window floats, dialogs and fixed-size windows do, and puts
+    /// it last in the tiling order.
+    pub fn init_window_layout(&mut self, window: &WindowElement) {
+        let floating = self.parent_window(window).is_some() || has_fixed_size(window);
+        let data = layout_data(window);
+        data.floating.set(floating);
+        data.order.set(self.next_tiling_order);
+        self.next_tiling_order += 1;
+    }
+
+    pub fn toggle_floating(&mut self, window: &WindowElement) {
+        let data = layout_data(window);
+        data.floating.set(!data.floating.get());
+        if data.floating.get() {
+            unset_tiled(window);
+            self.space.raise_element(window, true);
+        }
+
+        if let Some(output) = self.output_for_window(window) {
+            self.arrange_output(&output);
+        }
+    }
+
+    /// Exchanges the tiles of two tiled windows.
+    pub fn swap_tiled(&mut self, a: &WindowElement, b: &WindowElement) {
+        let (a_data, b_data) = (layout_data(a), layout_data(b));
+        a_data.order.swap(&b_data.order);
+        self.workspaces.swap_in_strips(a, b);
+
+        let outputs = [self.output_for_window(a), self.output_for_window(b)];
+        for output in outputs.iter().flatten() {
+            self.arrange_output(output);
+        }
+    }
+
+    pub fn set_workspace_layout(&mut self, output: &Output, layout: LayoutKind) {
+        let workspace = self.workspaces.active(output);
+        self.workspaces.set_layout(workspace, layout);
+        tracing::info!("Workspace {} uses the {} layout", workspace, layout.name());
+        self.arrange_output(output);
+    }
+
+    /// Usable area of the output minus the outer gaps. Half of the inner gap is taken from
+    /// every side of a tile, so tiles are `inner_gap` apart and `outer_gap` away from the edges.
+    fn tiling_area(&self, output: &Output) -> Rectangle<i32, Logical> {
+        let config = &self.config.layout;
+        let inset = config.outer_gap - config.inner_gap / 2;
+        let area = self.usable_output_area(output);
+        Rectangle::from_loc_and_size(
+            area.loc + (inset, inset).into(),
+            (area.size.w - 2 * inset, area.size.h - 2 * inset),
+        )
+    }
+
+    /// Moves and resizes the tiled windows on the output according to the layout of the
+    /// workspace shown there.
+    pub fn arrange_output(&mut self, output: &Output) {
+        let workspace = self.workspaces.active(output);
+        let kind = self.workspace_layout(workspace);
+
+        let mut windows = self
+            .space
+            .elements()
+            .filter(|window| window.alive() && !is_floating(window))
+            .filter(|window| !window.is_override_redirect())
+            .filter(|window| !window.is_maximized() && !window.is_fullscreen())
+            .filter(|window| self.output_for_window(window).as_ref() == Some(output))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if kind == LayoutKind::Scrolling {
+            self.arrange_strip(output, workspace, windows);
+        } else {
+            // Other layouts place every window again, the strip starts over when chosen again.
+            windows.extend(self.unpark_strip(output, workspace));
+            self.workspaces.remove_strip(workspace);
+
+            let Some(layout) = kind.layout(&self.config.layout) else {
+                windows.iter().for_each(unset_tiled);
+                return;
+            };
+            windows.sort_by_key(|window| layout_data(window).order.get());
+
+            let half_gap = self.config.layout.inner_gap / 2;
+            let area = self.tiling_area(output);
+            for (window, tile) in windows.iter().zip(layout.arrange(area, windows.len())) {
+                let geometry = tile_geometry(window, tile, half_gap);
+                set_tiled(window, geometry);
+                self.space.map_element(window.clone(), geometry.loc, false);
+            }
+        }
+
+        // Maximized and fullscreen windows follow changes of the output, e.g. a new panel.
+        let maximized = self
+            .space
+            .elements()
+            .filter(|window| window.is_maximized() || window.is_fullscreen())
+            .filter(|window| self.output_for_window(window).as_ref() == Some(output))
+            .cloned()
+            .collect::<Vec<_>>();
+        for window in maximized {
+            self.fit_to_output(&window, output);
+        }
+
+        // Mapping raised the tiled windows, floating ones and X11 menus stay on top of them.
+        let floating = self
+            .space
+            .elements()
+            .filter(|window| is_floating(window) || window.is_override_redirect())
+            .cloned()
+            .collect::<Vec<_>>();
+        for window in floating {
+            self.space.raise_element(&window, false);
+        }
+    }
+
+    /// Moves and resizes a maximized or fullscreen window to the area it covers on the output.
+    fn fit_to_output(&mut self, window: &WindowElement, output: &Output) {
+        let WindowElement::Xdg(xdg) = window else {
+            return;
+        };
+        let area = if window.is_fullscreen() {
+            let Some(output_geo) = self.space.output_geometry(output) else {
+                return;
+            };
+            output_geo
+        } else {
+            self.maximized_area(window, output)
+        };
+
+        let resized = xdg.toplevel().with_pending_state(|state| {
+            let resized = state.size != Some(area.size);
+            state.size = Some(area.size);
+            resized
+        });
+        if resized && initial_configure_sent(xdg.toplevel()) {
+            xdg.toplevel().send_configure();
+        }
+        if self.space.element_location(window) != Some(area.loc) {
+            self.space.map_element(window.clone(), area.loc, false);
+        }
+    }
+
+    pub fn arrange_all(&mut self) {
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        for output in outputs {
+            self.arrange_output(&output);
+        }
+    }
+}
@@ -0,0 +1,531 @@
+use std::time::{Duration, Instant};
+
+use smithay::{
+    output::Output,
+    utils::{IsAlive, Logical, Rectangle},
+};
+
+use crate::{render::window::WindowElement, state::NoWayState};
+
+use super::{layout_data, set_tiled, tile_geometry, LayoutKind};
+
+/// Share of the remaining scroll distance covered every [`SCROLL_FRAME`].
+const SCROLL_SPEED: f64 = 0.3;
+/// A frame at 60 Hz.
+const SCROLL_FRAME: Duration = Duration::from_micros(16_667);
+
+/// Horizontal strip of columns used by the scrolling layout. The strip is as wide as its
+/// columns; the output shows the part of it starting at the scroll offset. It only compares and
+/// clones its windows, so it is generic over them for the tests.
+#[derive(Debug)]
+pub struct Strip<W = WindowElement> {
+    /// Columns from left to right, each with its windows from top to bottom.
+    columns: Vec<Vec<W>>,
+    /// Scroll offset shown on the output.
+    offset: f64,
+    /// Scroll offset the view is animating towards.
+    target: f64,
+    /// When the scroll offset last moved, to keep the animation speed independent of the frame
+    /// rate.
+    stepped_at: Option<Instant>,
+    /// Windows of the columns outside the view. They are unmapped, so they are neither drawn
+    /// nor get input on a neighbouring output.
+    offscreen: Vec<W>,
+}
+
+impl<W> Default for Strip<W> {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            offset: 0.0,
+            target: 0.0,
+            stepped_at: None,
+            offscreen: Vec::new(),
+        }
+    }
+}
+
+fn column_width(area: Rectangle<i32, Logical>, ratio: f64) -> i32 {
+    ((area.size.w as f64 * ratio).round() as i32).max(1)
+}
+
+impl<W: Clone + PartialEq> Strip<W> {
+    pub fn contains(&self, window: &W) -> bool {
+        self.column_of(window).is_some()
+    }
+
+    fn column_of(&self, window: &W) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.contains(window))
+    }
+
+    pub fn remove(&mut self, window: &W) {
+        for column in &mut self.columns {
+            column.retain(|w| w != window);
+        }
+        self.columns.retain(|column| !column.is_empty());
+        self.offscreen.retain(|w| w != window);
+    }
+
+    /// Keeps only the given windows in the strip. Windows not in it yet open new columns right
+    /// of the focused one.
+    fn sync(&mut self, windows: &[W], focused: Option<&W>) {
+        for column in &mut self.columns {
+            column.retain(|window| windows.contains(window));
+        }
+        self.columns.retain(|column| !column.is_empty());
+
+        let mut index = focused
+            .and_then(|focused| self.column_of(focused))
+            .map_or(self.columns.len(), |index| index + 1);
+        for window in windows {
+            if !self.contains(window) {
+                self.columns.insert(index, vec![window.clone()]);
+                index += 1;
+            }
+        }
+    }
+
+    /// Exchanges the places of two windows, if both are in the strip.
+    pub fn swap(&mut self, a: &W, b: &W) {
+        let position = |window: &W| {
+            self.columns.iter().enumerate().find_map(|(index, column)| {
+                let row = column.iter().position(|w| w == window)?;
+                Some((index, row))
+            })
+        };
+        let (Some((a_index, a_row)), Some((b_index, b_row))) = (position(a), position(b)) else {
+            return;
+        };
+
+        self.columns[a_index][a_row] = b.clone();
+        self.columns[b_index][b_row] = a.clone();
+    }
+
+    /// Moves the window to the bottom of the column on its left.
+    fn consume(&mut self, window: &W) -> bool {
+        let Some(index) = self.column_of(window).filter(|index| *index > 0) else {
+            return false;
+        };
+
+        self.remove(window);
+        self.columns[index - 1].push(window.clone());
+        true
+    }
+
+    /// Moves the window out of its column into a new column on the right.
+    fn expel(&mut self, window: &W) -> bool {
+        let Some(index) = self
+            .column_of(window)
+            .filter(|index| self.columns[*index].len() > 1)
+        else {
+            return false;
+        };
+
+        self.remove(window);
+        self.columns.insert(index + 1, vec![window.clone()]);
+        true
+    }
+
+    /// Top window of the column `direction` columns away from the window's column.
+    fn window_beside(&self, window: &W, direction: isize) -> Option<&W> {
+        let index = self.column_of(window)?.checked_add_signed(direction)?;
+        self.columns.get(index)?.first()
+    }
+
+    /// Scrolls as little as possible to show the whole column of the window, and no further
+    /// than the ends of the strip.
+    fn scroll_to(&mut self, window: &W, area: Rectangle<i32, Logical>, ratio: f64) {
+        let width = column_width(area, ratio) as f64;
+        let view = area.size.w as f64;
+
+        if let Some(index) = self.column_of(window) {
+            let left = index as f64 * width;
+            if left < self.target {
+                self.target = left;
+            } else if left + width > self.target + view {
+                self.target = left + width - view;
+            }
+        }
+
+        let max = (self.columns.len() as f64 * width - view).max(0.0);
+        self.target = self.target.clamp(0.0, max);
+    }
+
+    /// Moves the scroll offset towards the target by the time passed since the last step.
+    /// Returns whether it moved.
+    fn step(&mut self, now: Instant) -> bool {
+        let distance = self.target - self.offset;
+        if distance == 0.0 {
+            self.stepped_at = None;
+            return false;
+        }
+
+        let elapsed = self.stepped_at.map_or(SCROLL_FRAME, |at| now - at);
+        self.stepped_at = Some(now);
+        if distance.abs() < 0.5 {
+            self.offset = self.target;
+        } else {
+            let frames = elapsed.as_secs_f64() / SCROLL_FRAME.as_secs_f64();
+            self.offset += distance * (1.0 - (1.0 - SCROLL_SPEED).powf(frames));
+        }
+        true
+    }
+
+    /// Tiles of the windows at the current scroll offset, in global coordinates.
+    fn tiles(
+        &self,
+        area: Rectangle<i32, Logical>,
+        ratio: f64,
+    ) -> Vec<(W, Rectangle<i32, Logical>)> {
+        let width = column_width(area, ratio);
+        let left = area.loc.x - self.offset.round() as i32;
+
+        let mut tiles = Vec::new();
+        for (index, column) in self.columns.iter().enumerate() {
+            let x = left + index as i32 * width;
+            let count = column.len() as i32;
+            for (row, window) in column.iter().enumerate() {
+                let top = area.size.h * row as i32 / count;
+                let bottom = area.size.h * (row as i32 + 1) / count;
+                tiles.push((
+                    window.clone(),
+                    Rectangle::from_loc_and_size((x, area.loc.y + top), (width, bottom - top)),
+                ));
+            }
+        }
+        tiles
+    }
+}
+
+impl NoWayState {
+    /// Arranges the tiled windows of a workspace using the scrolling layout.
+    pub(super) fn arrange_strip(
+        &mut self,
+        output: &Output,
+        workspace: usize,
+        mut windows: Vec<WindowElement>,
+    ) {
+        let focused = self.focused_window();
+        let area = self.tiling_area(output);
+        let ratio = self.config.layout.column_width;
+
+        // Windows outside the view are not mapped, but still belong to the strip.
+        let strip = self.workspaces.strip(workspace);
+        windows.extend(strip.offscreen.iter().filter(|w| w.alive()).cloned());
+        windows.sort_by_key(|window| layout_data(window).order.get());
+        strip.sync(&windows, focused.as_ref());
+        if let Some(focused) = focused.as_ref() {
+            strip.scroll_to(focused, area, ratio);
+        }
+
+        self.place_strip(output, true);
+    }
+
+    /// Maps the windows of the strip shown on the output at the current scroll offset, and
+    /// unmaps the ones outside the view. Windows the view is scrolling towards are mapped right
+    /// away. Only configures them when their size may have changed.
+    fn place_strip(&mut self, output: &Output, configure: bool) {
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return;
+        };
+        let workspace = self.workspaces.active(output);
+        let area = self.tiling_area(output);
+        let half_gap = self.config.layout.inner_gap / 2;
+        let strip = self.workspaces.strip(workspace);
+        let tiles = strip.tiles(area, self.config.layout.column_width);
+        let scroll = strip.offset.round() as i32 - strip.target.round() as i32;
+
+        let mut offscreen = Vec::new();
+        for (window, tile) in tiles {
+            let geometry = tile_geometry(&window, tile, half_gap);
+            if configure {
+                set_tiled(&window, geometry);
+            }
+
+            let mut target_tile = tile;
+            target_tile.loc.x += scroll;
+            if tile.overlaps(output_geo) || target_tile.overlaps(output_geo) {
+                self.space.map_element(window, geometry.loc, false);
+            } else {
+                self.space.unmap_elem(&window);
+                offscreen.push(window);
+            }
+        }
+        self.workspaces.strip(workspace).offscreen = offscreen;
+    }
+
+    /// Maps the windows the strip of the workspace keeps outside the view, before another
+    /// layout takes over the workspace. Returns them, so they are arranged too.
+    pub fn unpark_strip(&mut self, output: &Output, workspace: usize) -> Vec<WindowElement> {
+        let area = self.tiling_area(output);
+        let offscreen = std::mem::take(&mut self.workspaces.strip(workspace).offscreen);
+        let windows = offscreen
+            .into_iter()
+            .filter(|window| window.alive())
+            .collect::<Vec<_>>();
+        for window in &windows {
+            self.space.map_element(window.clone(), area.loc, false);
+        }
+        windows
+    }
+
+    /// Scrolls the strip holding the window so that its column is visible.
+    pub fn scroll_to_window(&mut self, window: &WindowElement) {
+        let Some(output) = self.output_for_window(window) else {
+            return;
+        };
+        let workspace = self.workspaces.active(&output);
+        if self.workspace_layout(workspace) != LayoutKind::Scrolling {
+            return;
+        }
+
+        let area = self.tiling_area(&output);
+        let ratio = self.config.layout.column_width;
+        self.workspaces
+            .strip(workspace)
+            .scroll_to(window, area, ratio);
+    }
+
+    /// Advances the scroll animations of the shown strips.
+    pub fn animate_strips(&mut self) {
+        let now = Instant::now();
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        for output in outputs {
+            let workspace = self.workspaces.active(&output);
+            if self.workspace_layout(workspace) == LayoutKind::Scrolling
+                && self.workspaces.strip(workspace).step(now)
+            {
+                self.place_strip(&output, false);
+            }
+        }
+    }
+
+    /// Moves the keyboard focus to the column `direction` columns left (negative) or right
+    /// (positive) of the focused window.
+    pub fn focus_column(&mut self, direction: isize) {
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+        let Some(workspace) = self.workspaces.strip_workspace(&window) else {
+            return;
+        };
+        let Some(next) = self
+            .workspaces
+            .strip(workspace)
+            .window_beside(&window, direction)
+            .cloned()
+        else {
+            return;
+        };
+
+        // The column may be outside the view, so it is scrolled to and mapped before the focus
+        // moves there.
+        if let Some(output) = self.output_for_window(&window) {
+            let area = self.tiling_area(&output);
+            let ratio = self.config.layout.column_width;
+            self.workspaces
+                .strip(workspace)
+                .scroll_to(&next, area, ratio);
+            self.place_strip(&output, false);
+        }
+        self.focus_window(&next);
+    }
+
+    /// Stacks the window at the bottom of the column on its left.
+    pub fn consume_into_column(&mut self, window: &WindowElement) {
+        let Some(workspace) = self.workspaces.strip_workspace(window) else {
+            return;
+        };
+        if self.workspaces.strip(workspace).consume(window) {
+            self.rearrange_window_output(window);
+        }
+    }
+
+    /// Takes the window out of its column into a new column on the right.
+    pub fn expel_from_column(&mut self, window: &WindowElement) {
+        let Some(workspace) = self.workspaces.strip_workspace(window) else {
+            return;
+        };
+        if self.workspaces.strip(workspace).expel(window) {
+            self.rearrange_window_output(window);
+        }
+    }
+
+    fn rearrange_window_output(&mut self, window: &WindowElement) {
+        if let Some(output) = self.output_for_window(window) {
+            self.arrange_output(&output);
+            self.scroll_to_window(window);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    fn strip(columns: &[&[u32]]) -> Strip<u32> {
+        Strip {
+            columns: columns.iter().map(|column| column.to_vec()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Area of a 1000x600 output at (10, 20), without gaps.
+    fn area() -> Rectangle<i32, Logical> {
+        rect(10, 20, 1000, 600)
+    }
+
+    #[test]
+    fn single_window_gets_one_column() {
+        let strip = strip(&[&[1]]);
+        assert_eq!(strip.tiles(area(), 0.5), vec![(1, rect(10, 20, 500, 600))]);
+    }
+
+    #[test]
+    fn columns_line_up_and_windows_share_their_height() {
+        let strip = strip(&[&[1], &[2, 3, 4]]);
+        let area = rect(0, 0, 1000, 601);
+        assert_eq!(
+            strip.tiles(area, 0.5),
+            vec![
+                (1, rect(0, 0, 500, 601)),
+                (2, rect(500, 0, 500, 200)),
+                (3, rect(500, 200, 500, 200)),
+                (4, rect(500, 400, 500, 201)),
+            ]
+        );
+    }
+
+    #[test]
+    fn tiles_follow_the_rounded_scroll_offset() {
+        let mut strip = strip(&[&[1], &[2], &[3]]);
+        strip.offset = 249.6;
+        let x = strip
+            .tiles(area(), 0.5)
+            .into_iter()
+            .map(|(_, tile)| tile.loc.x)
+            .collect::<Vec<_>>();
+        assert_eq!(x, vec![-240, 260, 760]);
+    }
+
+    #[test]
+    fn columns_are_at_least_one_pixel_wide() {
+        let strip = strip(&[&[1], &[2]]);
+        let tiles = strip.tiles(rect(0, 0, 1, 100), 0.1);
+        assert_eq!(tiles[0].1, rect(0, 0, 1, 100));
+        assert_eq!(tiles[1].1, rect(1, 0, 1, 100));
+    }
+
+    #[test]
+    fn scrolls_as_little_as_possible() {
+        let mut strip = strip(&[&[1], &[2], &[3], &[4]]);
+
+        strip.scroll_to(&2, area(), 0.5);
+        assert_eq!(strip.target, 0.0);
+
+        strip.scroll_to(&4, area(), 0.5);
+        assert_eq!(strip.target, 1000.0);
+
+        strip.scroll_to(&3, area(), 0.5);
+        assert_eq!(strip.target, 1000.0);
+
+        strip.scroll_to(&2, area(), 0.5);
+        assert_eq!(strip.target, 500.0);
+    }
+
+    #[test]
+    fn scrolling_stays_within_the_strip() {
+        let mut strip = strip(&[&[1]]);
+        strip.target = 300.0;
+        strip.scroll_to(&1, area(), 0.5);
+        assert_eq!(strip.target, 0.0);
+
+        // Unknown windows only clamp the target.
+        let mut strip = self::strip(&[&[1], &[2], &[3]]);
+        strip.target = 2000.0;
+        strip.scroll_to(&9, area(), 0.5);
+        assert_eq!(strip.target, 500.0);
+    }
+
+    #[test]
+    fn scroll_steps_depend_on_elapsed_time() {
+        let now = Instant::now();
+        let mut strip = strip(&[&[1]]);
+        strip.target = 100.0;
+
+        assert!(strip.step(now));
+        assert!((strip.offset - 30.0).abs() < 1e-9);
+
+        // Two frames cover the distance of two single steps.
+        assert!(strip.step(now + SCROLL_FRAME * 2));
+        assert!((strip.offset - 65.7).abs() < 1e-6);
+
+        strip.offset = 99.8;
+        assert!(strip.step(now + SCROLL_FRAME * 3));
+        assert_eq!(strip.offset, 100.0);
+        assert!(!strip.step(now + SCROLL_FRAME * 4));
+    }
+
+    #[test]
+    fn consume_moves_the_window_into_the_left_column() {
+        let mut strip = strip(&[&[1], &[2, 3]]);
+        assert!(strip.consume(&2));
+        assert_eq!(strip.columns, vec![vec![1, 2], vec![3]]);
+
+        assert!(strip.consume(&3));
+        assert_eq!(strip.columns, vec![vec![1, 2, 3]]);
+
+        assert!(!strip.consume(&1));
+        assert!(!strip.consume(&9));
+    }
+
+    #[test]
+    fn expel_opens_a_column_on_the_right() {
+        let mut strip = strip(&[&[1, 2], &[3]]);
+        assert!(strip.expel(&1));
+        assert_eq!(strip.columns, vec![vec![2], vec![1], vec![3]]);
+
+        assert!(!strip.expel(&1));
+        assert!(!strip.expel(&9));
+    }
+
+    #[test]
+    fn sync_opens_new_windows_right_of_the_focused_one() {
+        let mut strip = strip(&[&[1], &[2, 3], &[4]]);
+        strip.sync(&[1, 2, 4, 5, 6], Some(&2));
+        assert_eq!(
+            strip.columns,
+            vec![vec![1], vec![2], vec![5], vec![6], vec![4]]
+        );
+
+        strip.sync(&[5, 7], None);
+        assert_eq!(strip.columns, vec![vec![5], vec![7]]);
+    }
+
+    #[test]
+    fn removing_the_last_window_of_a_column_drops_it() {
+        let mut strip = strip(&[&[1], &[2, 3]]);
+        strip.offscreen = vec![1];
+        strip.remove(&1);
+        assert_eq!(strip.columns, vec![vec![2, 3]]);
+        assert!(strip.offscreen.is_empty());
+        assert_eq!(strip.window_beside(&2, -1), None);
+    }
+
+    #[test]
+    fn swap_exchanges_places() {
+        let mut strip = strip(&[&[1], &[2, 3]]);
+        strip.swap(&1, &3);
+        assert_eq!(strip.columns, vec![vec![3], vec![2, 1]]);
+        assert_eq!(strip.window_beside(&2, -1), Some(&3));
+
+        strip.swap(&1, &9);
+        assert_eq!(strip.columns, vec![vec![3], vec![2, 1]]);
+    }
+}
@@ -2,17 +2,44 @@ use smithay::reexports::{calloop::EventLoop, wayland_server::Display};
 use tracing::Level;
 
 use crate::{
-    backend::winit::initialize_winit,
+    backend::{BackendKind, RendererKind},
+    cli::{Args, USAGE},
+    config::{reload::watch_config, Config},
+    ipc::init_ipc,
     state::{NoWayData, NoWayState},
 };
 
 pub mod backend;
+pub mod bindings;
+pub mod cli;
+pub mod config;
 pub mod grabs;
 pub mod handlers;
+pub mod ipc;
+pub mod keyboard;
+pub mod layout;
+pub mod placement;
 pub mod render;
 pub mod state;
+pub mod workspace;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if args.version {
+        println!("noway {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
     if let Ok(env_filter) = tracing_subscriber::EnvFilter::try_from_env("NOWAY_LOG") {
         tracing::info!(
             "Logging is being initialized with env filter: {}",
@@ -31,14 +58,95 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("Initialized logging with default filter successfully");
     }
 
-    tracing::info!("Starting NoWay");
+    let config = match args.config_path.as_deref() {
+        Some(path) => Config::load_from(path),
+        None => Config::load(),
+    };
+    if args.validate_config {
+        return match config {
+            Ok(_) => {
+                println!("Config is valid");
+                Ok(())
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        };
+    }
+    let config = config?;
+
+    let backend = match args.backend {
+        Some(backend) => backend,
+        None => match std::env::var("NOWAY_BACKEND") {
+            Ok(backend) => backend
+                .parse()
+                .map_err(|_| format!("unknown backend: {}", backend))?,
+            Err(_) => BackendKind::Winit,
+        },
+    };
+
+    let renderer = match args.renderer {
+        Some(renderer) => renderer,
+        None => match std::env::var("NOWAY_RENDERER") {
+            Ok(renderer) => renderer
+                .parse()
+                .map_err(|_| format!("unknown renderer: {}", renderer))?,
+            Err(_) => RendererKind::default(),
+        },
+    };
+
+    tracing::info!(
+        "Starting NoWay with {:?} backend and {:?} renderer",
+        backend,
+        renderer
+    );
     let mut event_loop = EventLoop::try_new()?;
     let mut display = Display::new()?;
-    let state = NoWayState::try_new(event_loop.handle(), event_loop.get_signal(), &mut display)?;
+    watch_config(&event_loop.handle(), &config)?;
+
+    let state = NoWayState::try_new(
+        event_loop.handle(),
+        event_loop.get_signal(),
+        &mut display,
+        config,
+        args.socket_name.as_deref(),
+    )?;
     let mut data = NoWayData { state, display };
 
-    initialize_winit(&mut event_loop, &mut data)?;
+    backend.initialize(&mut event_loop, &mut data, renderer)?;
+    data.state.startup_command = args.command;
+    let mut xwayland_started = false;
+    if data.state.config.xwayland {
+        match data.state.start_xwayland() {
+            Ok(()) => xwayland_started = true,
+            Err(err) => tracing::error!("Failed to start Xwayland: {}", err),
+        }
+    }
+    tracing::info!(
+        "Listening on Wayland socket {}",
+        data.state.socket_name.to_string_lossy()
+    );
+
+    let ipc_socket = init_ipc(
+        &event_loop.handle(),
+        &data.state.socket_name.to_string_lossy(),
+    );
+    match &ipc_socket {
+        Ok(path) => tracing::info!("Listening for IPC requests on {}", path.display()),
+        Err(err) => tracing::warn!("Failed to start IPC: {}", err),
+    }
+
+    // With Xwayland, the command is spawned once it is ready.
+    if !xwayland_started {
+        data.state.spawn_startup_command();
+    }
+
     event_loop.run(None, &mut data, move |_| {})?;
 
+    if let Ok(path) = ipc_socket {
+        let _ = std::fs::remove_file(path);
+    }
+
     Ok(())
 }
@@ -0,0 +1,263 @@
+use std::str::FromStr;
+
+use smithay::{
+    desktop::space::SpaceElement,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, Size},
+};
+
+use crate::{
+    render::{decoration, window::WindowElement},
+    state::NoWayState,
+};
+
+/// Offset between windows placed with [`PlacementStrategy::Cascade`].
+const CASCADE_OFFSET: i32 = 32;
+/// Windows [`PlacementStrategy::FreeArea`] works around. With more windows on an output there is
+/// hardly any free area left anyway.
+const MAX_FREE_AREA_WINDOWS: usize = 32;
+
+/// Where new toplevels are mapped.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PlacementStrategy {
+    /// Centered on the output under the pointer.
+    #[default]
+    Center,
+    /// Down and to the right of the topmost window on the output, which is usually the one
+    /// mapped or focused last.
+    Cascade,
+    /// Centered in the largest area not covered by other windows.
+    FreeArea,
+}
+
+impl FromStr for PlacementStrategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "center" => Ok(Self::Center),
+            "cascade" => Ok(Self::Cascade),
+            "free_area" => Ok(Self::FreeArea),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Rectangle covered by a window including its decorations, for a window geometry at `location`.
+fn outer_geometry(
+    window: &WindowElement,
+    location: Point<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    let size = window.geometry().size;
+    if window.has_server_side_decorations() {
+        let mut frame = decoration::frame(size);
+        frame.loc += location;
+        frame
+    } else {
+        Rectangle::from_loc_and_size(location, size)
+    }
+}
+
+/// Moves `rect` into `area` as far as it fits, preferring to keep its top-left corner visible.
+fn clamp(rect: Rectangle<i32, Logical>, area: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    let max_x = area.loc.x + (area.size.w - rect.size.w).max(0);
+    let max_y = area.loc.y + (area.size.h - rect.size.h).max(0);
+    (
+        rect.loc.x.min(max_x).max(area.loc.x),
+        rect.loc.y.min(max_y).max(area.loc.y),
+    )
+        .into()
+}
+
+fn centered(size: Size<i32, Logical>, area: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    let x = area.loc.x + (area.size.w - size.w) / 2;
+    let y = area.loc.y + (area.size.h - size.h) / 2;
+    clamp(Rectangle::from_loc_and_size((x, y), size), area)
+}
+
+/// Largest rectangle inside `area` not intersecting any of `occupied`. For every band between a
+/// candidate top and bottom edge, the widest gap between the rectangles crossing the band is
+/// taken. Only the last [`MAX_FREE_AREA_WINDOWS`] rectangles are considered.
+fn largest_free_area(
+    area: Rectangle<i32, Logical>,
+    occupied: &[Rectangle<i32, Logical>],
+) -> Option<Rectangle<i32, Logical>> {
+    let occupied = &occupied[occupied.len().saturating_sub(MAX_FREE_AREA_WINDOWS)..];
+    let (area_top, area_bottom) = (area.loc.y, area.loc.y + area.size.h);
+    let area_right = area.loc.x + area.size.w;
+
+    let mut tops = vec![area_top];
+    let mut bottoms = vec![area_bottom];
+    for rect in occupied {
+        tops.push(rect.loc.y + rect.size.h);
+        bottoms.push(rect.loc.y);
+    }
+    for edges in [&mut tops, &mut bottoms] {
+        edges.retain(|edge| (area_top..=area_bottom).contains(edge));
+        edges.sort_unstable();
+        edges.dedup();
+    }
+
+    let mut best: Option<Rectangle<i32, Logical>> = None;
+    let area_of = |rect: &Rectangle<i32, Logical>| rect.size.w * rect.size.h;
+    let mut blocked = Vec::with_capacity(occupied.len());
+    for &top in &tops {
+        for &bottom in bottoms.iter().filter(|&&bottom| bottom > top) {
+            blocked.clear();
+            blocked.extend(
+                occupied
+                    .iter()
+                    .filter(|rect| rect.loc.y < bottom && rect.loc.y + rect.size.h > top)
+                    .map(|rect| (rect.loc.x, rect.loc.x + rect.size.w)),
+            );
+            blocked.sort_unstable();
+
+            // Walk the blocked ranges from left to right, the gaps between them are free.
+            let mut left = area.loc.x;
+            for (start, end) in blocked
+                .iter()
+                .copied()
+                .chain(std::iter::once((area_right, area_right)))
+            {
+                let right = start.min(area_right);
+                if right > left {
+                    let candidate =
+                        Rectangle::from_loc_and_size((left, top), (right - left, bottom - top));
+                    if best.map_or(true, |best| area_of(&candidate) > area_of(&best)) {
+                        best = Some(candidate);
+                    }
+                }
+                left = left.max(end);
+            }
+        }
+    }
+
+    best
+}
+
+impl NoWayState {
+    /// Finds the toplevel a transient (dialog) window belongs to.
+    pub fn parent_window(&self, window: &WindowElement) -> Option<WindowElement> {
+        match window {
+            WindowElement::Xdg(window) => self.window_for_surface(&window.toplevel().parent()?),
+            WindowElement::X11(window) => {
+                let parent = window.is_transient_for()?;
+                self.space
+                    .elements()
+                    .find(|w| matches!(w, WindowElement::X11(w) if w.window_id() == parent))
+                    .cloned()
+            }
+        }
+    }
+
+    /// Chooses the location of a new window's geometry, once its size is known.
+    pub fn place_window(&self, window: &WindowElement) -> Point<i32, Logical> {
+        let frame = outer_geometry(window, (0, 0).into());
+        let to_location = |outer: Point<i32, Logical>| outer - frame.loc;
+
+        // Dialogs are centered over their parent, but stay on the output.
+        if let Some(parent) = self.parent_window(window) {
+            let parent_geo = self.space.element_geometry(&parent).unwrap();
+            let output = self.space.outputs_for_element(&parent).into_iter().next();
+            let area = output.map_or(parent_geo, |output| self.usable_output_area(&output));
+            let center = parent_geo.loc + parent_geo.size.downscale(2).to_point();
+            let outer = Rectangle::from_loc_and_size(
+                center - frame.size.downscale(2).to_point(),
+                frame.size,
+            );
+            return to_location(clamp(outer, area));
+        }
+
+        let Some(output) = self
+            .space
+            .output_under(self.pointer_location)
+            .next()
+            .or_else(|| self.space.outputs().next())
+            .cloned()
+        else {
+            return (0, 0).into();
+        };
+
+        // Windows that asked to be fullscreen or maximized before being mapped were sized for
+        // the output under the pointer.
+        if window.is_fullscreen() {
+            return self.space.output_geometry(&output).unwrap().loc;
+        }
+        if window.is_maximized() {
+            return self.maximized_area(window, &output).loc;
+        }
+
+        let area = self.usable_output_area(&output);
+
+        let windows_on_output = self
+            .space
+            .elements()
+            .filter(|w| w != window)
+            .filter_map(|w| {
+                let location = self.space.element_location(w)?;
+                Some(outer_geometry(w, location))
+            })
+            .filter(|rect| rect.overlaps(area))
+            .collect::<Vec<_>>();
+
+        let outer = match self.config.placement {
+            PlacementStrategy::Center => centered(frame.size, area),
+            PlacementStrategy::Cascade => match windows_on_output.last() {
+                Some(last) => {
+                    let next = last.loc + Point::from((CASCADE_OFFSET, CASCADE_OFFSET));
+                    let fits = area.contains_rect(Rectangle::from_loc_and_size(next, frame.size));
+                    if fits {
+                        next
+                    } else {
+                        // Start over from the top-left corner once the cascade leaves the output.
+                        area.loc
+                    }
+                }
+                None => area.loc,
+            },
+            PlacementStrategy::FreeArea => match largest_free_area(area, &windows_on_output) {
+                Some(free) => centered(frame.size, free),
+                None => centered(frame.size, area),
+            },
+        };
+
+        to_location(outer)
+    }
+
+    /// Maps a toplevel that is waiting for its first buffer, once it committed one. Mapping it
+    /// before would place it without knowing its size.
+    pub fn map_pending_window(&mut self, surface: &WlSurface) {
+        let Some(index) = self
+            .pending_windows
+            .iter()
+            .position(|window| window.wl_surface().as_ref() == Some(surface))
+        else {
+            return;
+        };
+
+        let window = &self.pending_windows[index];
+        if let WindowElement::Xdg(xdg) = window {
+            xdg.on_commit();
+        }
+        let size = window.geometry().size;
+        if size.w <= 0 || size.h <= 0 {
+            return;
+        }
+
+        let window = self.pending_windows.remove(index);
+        let location = self.place_window(&window);
+        self.init_window_layout(&window);
+        self.space.map_element(window.clone(), location, false);
+
+        if let Some(output) = self.output_for_window(&window) {
+            self.arrange_output(&output);
+        }
+    }
+
+    pub fn pending_window_for_surface(&self, surface: &WlSurface) -> Option<WindowElement> {
+        self.pending_windows
+            .iter()
+            .find(|window| window.wl_surface().as_ref() == Some(surface))
+            .cloned()
+    }
+}
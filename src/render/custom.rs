@@ -1,6 +1,7 @@
 use smithay::{
     backend::renderer::{
-        element::surface::WaylandSurfaceRenderElement, ImportAll, ImportMem, Renderer,
+        element::{solid::SolidColorRenderElement, surface::WaylandSurfaceRenderElement},
+        ImportAll, ImportMem, Renderer,
     },
     render_elements,
 };
@@ -12,6 +13,7 @@ render_elements! {
         R: ImportAll + ImportMem;
     Pointer=PointerRenderElement<R>,
     Surface=WaylandSurfaceRenderElement<R>,
+    Notice=SolidColorRenderElement,
 }
 
 impl<R> std::fmt::Debug for CustomRenderElements<R>
@@ -23,6 +25,7 @@ where
         match self {
             Self::Pointer(arg0) => f.debug_tuple("Pointer").field(arg0).finish(),
             Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Notice(arg0) => f.debug_tuple("Notice").field(arg0).finish(),
             Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
         }
     }
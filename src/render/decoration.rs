@@ -0,0 +1,207 @@
+use std::cell::RefCell;
+
+use smithay::{
+    backend::renderer::{
+        element::{solid::SolidColorRenderElement, Id},
+        utils::CommitCounter,
+    },
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size},
+};
+
+use crate::{grabs::resize_grab::ResizeEdge, render::text::Text};
+
+pub const BORDER_WIDTH: i32 = 4;
+pub const TITLE_HEIGHT: i32 = 24;
+const BUTTON_MARGIN: i32 = 4;
+
+const FOCUSED_COLOR: [f32; 4] = [0.28, 0.42, 0.62, 1.0];
+const UNFOCUSED_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+const CLOSE_COLOR: [f32; 4] = [0.85, 0.3, 0.3, 1.0];
+const MAXIMIZE_COLOR: [f32; 4] = [0.85, 0.7, 0.3, 1.0];
+const TITLE_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+/// Part of the server-side decorations hit by the pointer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecorationHit {
+    TitleBar,
+    Close,
+    Maximize,
+    Border(ResizeEdge),
+}
+
+/// Rectangle covered by the decorations, relative to the top-left corner of the window geometry.
+pub fn frame(size: Size<i32, Logical>) -> Rectangle<i32, Logical> {
+    Rectangle::from_loc_and_size(
+        (-BORDER_WIDTH, -TITLE_HEIGHT - BORDER_WIDTH),
+        (
+            size.w + 2 * BORDER_WIDTH,
+            size.h + TITLE_HEIGHT + 2 * BORDER_WIDTH,
+        ),
+    )
+}
+
+/// Window geometry that fits into `area` together with its decorations.
+pub fn content_area(area: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+    let frame = frame((0, 0).into());
+    Rectangle::from_loc_and_size(
+        area.loc - frame.loc,
+        (
+            (area.size.w - frame.size.w).max(1),
+            (area.size.h - frame.size.h).max(1),
+        ),
+    )
+}
+
+fn title_bar(size: Size<i32, Logical>) -> Rectangle<i32, Logical> {
+    Rectangle::from_loc_and_size((0, -TITLE_HEIGHT), (size.w, TITLE_HEIGHT))
+}
+
+/// Part of the title bar left of the buttons, where the title is drawn.
+fn title_area(size: Size<i32, Logical>) -> Rectangle<i32, Logical> {
+    let buttons = 2 * TITLE_HEIGHT;
+    Rectangle::from_loc_and_size(
+        (2 * BUTTON_MARGIN, -TITLE_HEIGHT),
+        (size.w - buttons - 2 * BUTTON_MARGIN, TITLE_HEIGHT),
+    )
+}
+
+fn button(size: Size<i32, Logical>, index: i32) -> Rectangle<i32, Logical> {
+    let side = TITLE_HEIGHT - 2 * BUTTON_MARGIN;
+    Rectangle::from_loc_and_size(
+        (
+            size.w - (index + 1) * TITLE_HEIGHT + BUTTON_MARGIN,
+            -TITLE_HEIGHT + BUTTON_MARGIN,
+        ),
+        (side, side),
+    )
+}
+
+fn close_button(size: Size<i32, Logical>) -> Rectangle<i32, Logical> {
+    button(size, 0)
+}
+
+fn maximize_button(size: Size<i32, Logical>) -> Rectangle<i32, Logical> {
+    button(size, 1)
+}
+
+fn borders(size: Size<i32, Logical>) -> [Rectangle<i32, Logical>; 4] {
+    let frame = frame(size);
+    [
+        // Top and bottom span the corners, left and right only the height in between.
+        Rectangle::from_loc_and_size(frame.loc, (frame.size.w, BORDER_WIDTH)),
+        Rectangle::from_loc_and_size((frame.loc.x, size.h), (frame.size.w, BORDER_WIDTH)),
+        Rectangle::from_loc_and_size(
+            (frame.loc.x, -TITLE_HEIGHT),
+            (BORDER_WIDTH, size.h + TITLE_HEIGHT),
+        ),
+        Rectangle::from_loc_and_size(
+            (size.w, -TITLE_HEIGHT),
+            (BORDER_WIDTH, size.h + TITLE_HEIGHT),
+        ),
+    ]
+}
+
+/// Finds the part of the decorations at `point`, relative to the top-left corner of the window
+/// geometry of the given size.
+pub fn hit(size: Size<i32, Logical>, point: Point<f64, Logical>) -> Option<DecorationHit> {
+    let frame = frame(size).to_f64();
+    if !frame.contains(point) {
+        return None;
+    }
+
+    if close_button(size).to_f64().contains(point) {
+        return Some(DecorationHit::Close);
+    }
+    if maximize_button(size).to_f64().contains(point) {
+        return Some(DecorationHit::Maximize);
+    }
+    if title_bar(size).to_f64().contains(point) {
+        return Some(DecorationHit::TitleBar);
+    }
+
+    let mut edges = ResizeEdge::empty();
+    if point.x < 0.0 {
+        edges |= ResizeEdge::LEFT;
+    } else if point.x >= size.w as f64 {
+        edges |= ResizeEdge::RIGHT;
+    }
+    if point.y < (-TITLE_HEIGHT) as f64 {
+        edges |= ResizeEdge::TOP;
+    } else if point.y >= size.h as f64 {
+        edges |= ResizeEdge::BOTTOM;
+    }
+
+    // Anything else inside the frame is the window itself.
+    (!edges.is_empty()).then_some(DecorationHit::Border(edges))
+}
+
+/// Render element ids of a window's decorations, kept in the window's user data so damage is
+/// tracked across frames.
+#[derive(Debug)]
+pub struct DecorationState {
+    title: Text,
+    title_bar: Id,
+    close: Id,
+    maximize: Id,
+    borders: [Id; 4],
+    commit: CommitCounter,
+    focused: bool,
+}
+
+impl Default for DecorationState {
+    fn default() -> Self {
+        Self {
+            title: Text::new("", TITLE_COLOR),
+            title_bar: Id::new(),
+            close: Id::new(),
+            maximize: Id::new(),
+            borders: [Id::new(), Id::new(), Id::new(), Id::new()],
+            commit: CommitCounter::default(),
+            focused: false,
+        }
+    }
+}
+
+impl DecorationState {
+    /// Render elements of the decorations, front to back. `location` is the physical position
+    /// of the top-left corner of the window geometry.
+    pub fn render_elements(
+        state: &RefCell<Self>,
+        size: Size<i32, Logical>,
+        title: &str,
+        focused: bool,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+    ) -> Vec<SolidColorRenderElement> {
+        let mut state = state.borrow_mut();
+        state.title.update(title, TITLE_COLOR);
+        if state.focused != focused {
+            state.focused = focused;
+            state.commit.increment();
+        }
+
+        let color = if focused {
+            FOCUSED_COLOR
+        } else {
+            UNFOCUSED_COLOR
+        };
+        let element = |id: &Id, rect: Rectangle<i32, Logical>, color| {
+            let mut geometry = rect.to_physical_precise_round(scale);
+            geometry.loc += location;
+            SolidColorRenderElement::new(id.clone(), geometry, state.commit, color)
+        };
+
+        let mut elements = vec![
+            element(&state.close, close_button(size), CLOSE_COLOR),
+            element(&state.maximize, maximize_button(size), MAXIMIZE_COLOR),
+        ];
+        let area = title_area(size);
+        elements.extend(state.title.render_elements(area, location, scale));
+        elements.push(element(&state.title_bar, title_bar(size), color));
+        for (id, rect) in state.borders.iter().zip(borders(size)) {
+            elements.push(element(id, rect, color));
+        }
+
+        elements
+    }
+}
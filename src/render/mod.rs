@@ -1,22 +1,31 @@
 use smithay::{
     backend::renderer::{
         damage::{Error as OutputDamageTrackerError, OutputDamageTracker},
-        element::{RenderElement, RenderElementStates, Wrap},
+        element::{
+            surface::WaylandSurfaceRenderElement, AsRenderElements, RenderElement,
+            RenderElementStates, Wrap,
+        },
         ImportAll, ImportMem, Renderer,
     },
-    desktop::space::{space_render_elements, Space, SpaceRenderElements},
+    desktop::{
+        layer_map_for_output,
+        space::{space_render_elements, SpaceElement, SpaceRenderElements},
+    },
     output::Output,
     render_elements,
-    utils::{Physical, Rectangle},
+    utils::{Physical, Rectangle, Scale},
+    wayland::shell::wlr_layer::Layer,
 };
 
-use self::{
-    custom::CustomRenderElements,
-    window::{WindowElement, WindowRenderElement},
-};
+use crate::state::NoWayState;
+
+use self::{custom::CustomRenderElements, window::WindowRenderElement};
 
 pub mod custom;
+pub mod decoration;
+pub mod notice;
 pub mod pointer;
+pub mod text;
 pub mod window;
 
 render_elements! {
@@ -24,6 +33,7 @@ render_elements! {
         where R: ImportAll + ImportMem;
     Space=SpaceRenderElements<R, E>,
     Window=Wrap<E>,
+    Layer=WaylandSurfaceRenderElement<R>,
     Custom=CustomRenderElements<R>,
 }
 
@@ -37,6 +47,7 @@ where
         match self {
             Self::Space(arg0) => f.debug_tuple("Space").field(arg0).finish(),
             Self::Window(arg0) => f.debug_tuple("Window").field(arg0).finish(),
+            Self::Layer(arg0) => f.debug_tuple("Layer").field(arg0).finish(),
             Self::Custom(arg0) => f.debug_tuple("Custom").field(arg0).finish(),
             Self::_GenericCatcher(arg0) => f.debug_tuple("_GenericCatcher").field(arg0).finish(),
         }
@@ -45,7 +56,7 @@ where
 
 pub fn output_elements<R>(
     output: &Output,
-    space: &Space<WindowElement>,
+    state: &NoWayState,
     custom_elements: impl IntoIterator<Item = CustomRenderElements<R>>,
     renderer: &mut R,
 ) -> Vec<OutputRenderElements<R, WindowRenderElement<R>>>
@@ -58,7 +69,59 @@ where
         .map(OutputRenderElements::from)
         .collect::<Vec<_>>();
 
-    let space_elements = space_render_elements(renderer, [space], output).unwrap();
+    if let Some(notice) = state.config_notice.as_ref() {
+        if let Some(output_geo) = state.space.output_geometry(output) {
+            let scale = Scale::from(output.current_scale().fractional_scale());
+            output_render_elements.extend(
+                notice
+                    .render_elements(output_geo.size, scale)
+                    .into_iter()
+                    .map(|element| OutputRenderElements::from(CustomRenderElements::from(element))),
+            );
+        }
+    }
+
+    // A fullscreen window hides everything but the overlay layer, which keeps lock screens and
+    // notifications visible. Without overlay surfaces the window is the only element left, so
+    // its buffer can be scanned out directly.
+    if let Some(window) = state.fullscreen_window(output) {
+        let output_geo = state.space.output_geometry(output).unwrap();
+        let scale = Scale::from(output.current_scale().fractional_scale());
+
+        let map = layer_map_for_output(output);
+        for layer in map.layers_on(Layer::Overlay).rev() {
+            let location = map.layer_geometry(layer).unwrap().loc;
+            output_render_elements.extend(
+                AsRenderElements::<R>::render_elements::<WaylandSurfaceRenderElement<R>>(
+                    layer,
+                    renderer,
+                    location.to_physical_precise_round(scale),
+                    scale,
+                )
+                .into_iter()
+                .map(OutputRenderElements::Layer),
+            );
+        }
+
+        let location =
+            state.space.element_location(window).unwrap() - window.geometry().loc - output_geo.loc;
+        output_render_elements.extend(
+            AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
+                window,
+                renderer,
+                location.to_physical_precise_round(scale),
+                scale,
+            )
+            .into_iter()
+            .map(|element| OutputRenderElements::Window(Wrap::from(element))),
+        );
+
+        return output_render_elements;
+    }
+
+    // Besides the windows this includes the layer surfaces of the output: background and bottom
+    // layers behind the windows, top and overlay layers in front of them.
+    let space_elements = space_render_elements(renderer, [&state.space], output).unwrap();
     output_render_elements.extend(space_elements.into_iter().map(OutputRenderElements::Space));
 
     output_render_elements
@@ -67,7 +130,7 @@ where
 type Damage = Vec<Rectangle<i32, Physical>>;
 pub fn render_output<R>(
     output: &Output,
-    space: &Space<WindowElement>,
+    state: &NoWayState,
     custom_elements: impl IntoIterator<Item = CustomRenderElements<R>>,
     renderer: &mut R,
     damage_tracker: &mut OutputDamageTracker,
@@ -77,6 +140,6 @@ where
     R: Renderer + ImportAll + ImportMem,
     R::TextureId: Clone + 'static,
 {
-    let elements = output_elements(output, space, custom_elements, renderer);
-    damage_tracker.render_output(renderer, age, &elements, [0.1, 0.1, 0.1, 1.0])
+    let elements = output_elements(output, state, custom_elements, renderer);
+    damage_tracker.render_output(renderer, age, &elements, state.config.output.background)
 }
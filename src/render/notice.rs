@@ -0,0 +1,60 @@
+use smithay::{
+    backend::renderer::{
+        element::{solid::SolidColorRenderElement, Id},
+        utils::CommitCounter,
+    },
+    utils::{Logical, Rectangle, Scale, Size},
+};
+
+use super::text::Text;
+
+const NOTICE_HEIGHT: i32 = 24;
+const NOTICE_PADDING: i32 = 8;
+const NOTICE_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+const NOTICE_TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// A bar drawn across the top of every output to signal a problem that needs attention, e.g. a
+/// config file that failed to load. The message is shown in the bar.
+#[derive(Debug)]
+pub struct Notice {
+    pub message: String,
+    text: Text,
+    id: Id,
+    commit: CommitCounter,
+}
+
+impl Notice {
+    pub fn new(message: impl Into<String>) -> Self {
+        let message = message.into();
+        // The bar has room for a single line.
+        let line = message.lines().collect::<Vec<_>>().join(" ");
+        Self {
+            text: Text::new(&line, NOTICE_TEXT_COLOR),
+            message,
+            id: Id::new(),
+            commit: CommitCounter::default(),
+        }
+    }
+
+    /// Render elements of the bar and its message, front to back.
+    pub fn render_elements(
+        &self,
+        output_size: Size<i32, Logical>,
+        scale: Scale<f64>,
+    ) -> Vec<SolidColorRenderElement> {
+        let bar = Rectangle::from_loc_and_size((0, 0), (output_size.w, NOTICE_HEIGHT));
+        let text_area = Rectangle::from_loc_and_size(
+            (NOTICE_PADDING, 0),
+            (output_size.w - 2 * NOTICE_PADDING, NOTICE_HEIGHT),
+        );
+
+        let mut elements = self.text.render_elements(text_area, (0, 0).into(), scale);
+        elements.push(SolidColorRenderElement::new(
+            self.id.clone(),
+            bar.to_physical_precise_round(scale),
+            self.commit,
+            NOTICE_COLOR,
+        ));
+        elements
+    }
+}
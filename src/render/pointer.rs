@@ -1,17 +1,66 @@
 use smithay::{
-    backend::renderer::{
-        element::{
-            surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
-            texture::{TextureBuffer, TextureRenderElement},
-            AsRenderElements,
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+                texture::{TextureBuffer, TextureRenderElement},
+                AsRenderElements,
+            },
+            ImportAll, ImportMem, Renderer, Texture,
         },
-        ImportAll, Renderer, Texture,
     },
     input::pointer::CursorImageStatus,
     render_elements,
-    utils::{Physical, Point, Scale},
+    utils::{Buffer, Physical, Point, Scale, Size, Transform},
 };
 
+pub const DEFAULT_CURSOR_SIZE: i32 = 16;
+
+/// Pixels of a plain arrow cursor, [`DEFAULT_CURSOR_SIZE`] pixels wide and high.
+///
+/// The arrow only uses gray levels, so the same bytes are valid as both RGBA and little-endian
+/// Argb8888 data.
+pub fn default_cursor_pixels() -> Vec<u8> {
+    let size = DEFAULT_CURSOR_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let pixel: [u8; 4] = if x > y {
+                [0x00, 0x00, 0x00, 0x00]
+            } else if x == 0 || x == y || y == size - 1 {
+                [0x00, 0x00, 0x00, 0xff]
+            } else {
+                [0xff, 0xff, 0xff, 0xff]
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+
+    data
+}
+
+/// Builds a plain arrow cursor for backends that have no host cursor to fall back on.
+pub fn default_cursor_texture<R>(renderer: &mut R) -> Result<TextureBuffer<R::TextureId>, R::Error>
+where
+    R: Renderer + ImportMem,
+{
+    let buffer_size = Size::<i32, Buffer>::from((DEFAULT_CURSOR_SIZE, DEFAULT_CURSOR_SIZE));
+    let texture = renderer.import_memory(
+        &default_cursor_pixels(),
+        Fourcc::Argb8888,
+        buffer_size,
+        false,
+    )?;
+    Ok(TextureBuffer::from_texture(
+        renderer,
+        texture,
+        1,
+        Transform::Normal,
+        None,
+    ))
+}
+
 pub struct PointerElement<T: Texture> {
     texture: Option<TextureBuffer<T>>,
     status: CursorImageStatus,
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{cell::RefCell, time::Duration};
 
 use smithay::{
     backend::{
@@ -27,19 +27,30 @@ use smithay::{
     },
     output::Output,
     reexports::{
-        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_protocols::{
+            wp::presentation_time::server::wp_presentation_feedback,
+            xdg::{
+                decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+                shell::server::xdg_toplevel,
+            },
+        },
         wayland_server::protocol::wl_surface::WlSurface,
     },
     render_elements,
     utils::{user_data::UserDataMap, IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial},
     wayland::{
-        compositor::SurfaceData as WlSurfaceData, dmabuf::DmabufFeedback, seat::WaylandFocus,
+        compositor::{with_states, SurfaceData as WlSurfaceData},
+        dmabuf::DmabufFeedback,
+        seat::WaylandFocus,
+        shell::xdg::XdgToplevelSurfaceData,
     },
     xwayland::X11Surface,
 };
 
 use crate::state::NoWayState;
 
+use super::decoration::{self, DecorationHit, DecorationState};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum WindowElement {
     Xdg(Window),
@@ -162,6 +173,11 @@ impl WindowElement {
         matches!(self, Self::Xdg(_))
     }
 
+    /// Whether this is an X11 menu or tooltip, which places itself and never takes the focus.
+    pub fn is_override_redirect(&self) -> bool {
+        matches!(self, Self::X11(w) if w.is_override_redirect())
+    }
+
     pub fn wl_surface(&self) -> Option<WlSurface> {
         match self {
             Self::Xdg(w) => w.wl_surface(),
@@ -175,6 +191,83 @@ impl WindowElement {
             Self::X11(w) => w.user_data(),
         }
     }
+
+    /// Title set by the client, empty if it did not set one.
+    pub fn title(&self) -> String {
+        match self {
+            Self::Xdg(w) => with_states(w.toplevel().wl_surface(), |states| {
+                let data = states.data_map.get::<XdgToplevelSurfaceData>().unwrap();
+                data.lock().unwrap().title.clone().unwrap_or_default()
+            }),
+            Self::X11(w) => w.title(),
+        }
+    }
+
+    /// Whether the compositor draws the title bar and borders of this window. X11 clients
+    /// decorate themselves.
+    pub fn has_server_side_decorations(&self) -> bool {
+        match self {
+            Self::Xdg(w) => {
+                w.toplevel().current_state().decoration_mode == Some(DecorationMode::ServerSide)
+                    && !self.is_fullscreen()
+            }
+            Self::X11(_) => false,
+        }
+    }
+
+    /// Asks the client to close the window.
+    pub fn send_close(&self) {
+        match self {
+            Self::Xdg(w) => w.toplevel().send_close(),
+            Self::X11(w) => {
+                if let Err(err) = w.close() {
+                    tracing::warn!("Failed to close X11 window: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Whether the window is maximized, or about to be once it acknowledges the configure.
+    pub fn is_maximized(&self) -> bool {
+        match self {
+            Self::Xdg(w) => w
+                .toplevel()
+                .with_pending_state(|state| state.states.contains(xdg_toplevel::State::Maximized)),
+            Self::X11(_) => false,
+        }
+    }
+
+    /// Whether the window is fullscreen, or about to be once it acknowledges the configure.
+    pub fn is_fullscreen(&self) -> bool {
+        match self {
+            Self::Xdg(w) => w
+                .toplevel()
+                .with_pending_state(|state| state.states.contains(xdg_toplevel::State::Fullscreen)),
+            Self::X11(_) => false,
+        }
+    }
+
+    pub fn is_activated(&self) -> bool {
+        match self {
+            Self::Xdg(w) => w
+                .toplevel()
+                .current_state()
+                .states
+                .contains(xdg_toplevel::State::Activated),
+            Self::X11(w) => w.is_activated(),
+        }
+    }
+
+    /// Finds the part of the server-side decorations under `point`, which is relative to the
+    /// render location of the window like for [`SpaceElement::is_in_input_region`].
+    pub fn decoration_under(&self, point: Point<f64, Logical>) -> Option<DecorationHit> {
+        if !self.has_server_side_decorations() {
+            return None;
+        }
+
+        let geometry = self.geometry();
+        decoration::hit(geometry.size, point - geometry.loc.to_f64())
+    }
 }
 
 impl IsAlive for WindowElement {
@@ -294,13 +387,27 @@ impl SpaceElement for WindowElement {
     }
 
     fn bbox(&self) -> Rectangle<i32, Logical> {
-        match self {
+        let bbox = match self {
             Self::Xdg(w) => w.bbox(),
             Self::X11(w) => w.bbox(),
+        };
+
+        // Decorations are drawn around the window geometry, outside of the client's surfaces.
+        if self.has_server_side_decorations() {
+            let geometry = self.geometry();
+            let mut frame = decoration::frame(geometry.size);
+            frame.loc += geometry.loc;
+            bbox.merge(frame)
+        } else {
+            bbox
         }
     }
 
     fn is_in_input_region(&self, point: &Point<f64, Logical>) -> bool {
+        if self.decoration_under(*point).is_some() {
+            return true;
+        }
+
         match self {
             Self::Xdg(w) => w.is_in_input_region(point),
             Self::X11(w) => w.is_in_input_region(point),
@@ -359,16 +466,32 @@ where
         location: Point<i32, Physical>,
         scale: Scale<f64>,
     ) -> Vec<C> {
-        match self {
+        let mut elements = match self {
+            // Popups tracked by the `PopupManager` are rendered by the window itself, in front of
+            // the toplevel surface.
             Self::Xdg(xdg) => AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
                 xdg, renderer, location, scale,
             ),
             Self::X11(x11) => AsRenderElements::<R>::render_elements::<WindowRenderElement<R>>(
                 x11, renderer, location, scale,
             ),
+        };
+
+        if self.has_server_side_decorations() {
+            let geometry = self.geometry();
+            let user_data = self.user_data();
+            user_data.insert_if_missing(|| RefCell::new(DecorationState::default()));
+            let decorations = DecorationState::render_elements(
+                user_data.get::<RefCell<DecorationState>>().unwrap(),
+                geometry.size,
+                &self.title(),
+                self.is_activated(),
+                location + geometry.loc.to_physical_precise_round(scale),
+                scale,
+            );
+            elements.extend(decorations.into_iter().map(WindowRenderElement::Decoration));
         }
-        .into_iter()
-        .map(C::from)
-        .collect()
+
+        elements.into_iter().map(C::from).collect()
     }
 }
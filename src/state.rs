@@ -1,12 +1,21 @@
 use std::{
     ffi::OsString,
-    os::fd::AsRawFd,
+    os::{
+        fd::AsRawFd,
+        unix::{net::UnixStream, process::CommandExt},
+    },
+    process::Command,
     sync::{Arc, Mutex},
     time::Instant,
 };
 
+use nix::{
+    libc,
+    unistd::{fork, ForkResult},
+};
 use smithay::{
-    desktop::Space,
+    backend::session::libseat::LibSeatSession,
+    desktop::{PopupManager, Space},
     input::{pointer::CursorImageStatus, Seat, SeatState},
     reexports::{
         calloop::{generic::Generic, Interest, LoopHandle, LoopSignal, Mode, PostAction},
@@ -17,12 +26,32 @@ use smithay::{
     },
     utils::{Logical, Point},
     wayland::{
-        compositor::CompositorState, data_device::DataDeviceState, output::OutputManagerState,
-        shell::xdg::XdgShellState, shm::ShmState, socket::ListeningSocketSource,
+        compositor::CompositorState,
+        data_device::DataDeviceState,
+        output::OutputManagerState,
+        primary_selection::PrimarySelectionState,
+        relative_pointer::RelativePointerManagerState,
+        shell::{
+            wlr_layer::WlrLayerShellState,
+            xdg::{decoration::XdgDecorationState, XdgShellState},
+        },
+        shm::ShmState,
+        socket::ListeningSocketSource,
     },
+    xwayland::{xwm::X11Wm, XWayland},
 };
 
-use crate::render::window::WindowElement;
+use crate::{
+    config::Config,
+    grabs::touch_grab::TouchGrab,
+    handlers::{
+        data_control::DataControlState, pointer_constraints::PointerConstraintsState,
+        touch::TouchPoint,
+    },
+    keyboard::{keymap_layouts, xkb_config},
+    render::{notice::Notice, window::WindowElement},
+    workspace::Workspaces,
+};
 
 pub struct ClientState;
 impl ClientData for ClientState {
@@ -41,21 +70,64 @@ pub struct NoWayState {
     pub start_time: Instant,
     pub loop_handle: LoopHandle<'static, NoWayData>,
     pub loop_signal: LoopSignal,
+    /// Session of the udev backend, used to switch virtual terminals.
+    pub session: Option<LibSeatSession>,
+    pub config: Config,
+    /// Set when a new config was applied, so backends can pick up the changes on the next frame.
+    pub config_changed: bool,
+    pub config_notice: Option<Notice>,
 
     pub socket_name: OsString,
     pub space: Space<WindowElement>,
+    /// Toplevels that are not mapped yet because they did not commit a buffer.
+    pub pending_windows: Vec<WindowElement>,
+    pub workspaces: Workspaces,
+    /// Tiling order of the next mapped window, see `init_window_layout`.
+    pub next_tiling_order: u64,
+    pub popups: PopupManager,
 
     pub cursor_status: Arc<Mutex<CursorImageStatus>>,
     pub pointer_location: Point<f64, Logical>,
+    /// Last position reported by absolute pointer motion, which can differ from the pointer
+    /// location while a constraint holds.
+    pub absolute_pointer_location: Option<Point<f64, Logical>>,
     pub seat: Seat<Self>,
+    pub touch_points: Vec<TouchPoint>,
+    /// Window following a touch point, see `TouchGrab`.
+    pub touch_grab: Option<TouchGrab>,
+    /// Keys whose press was handled by the compositor, so their release is not sent to clients.
+    pub suppressed_keys: Vec<u32>,
+    /// Index of the active layout in the keyboard config.
+    pub keyboard_layout: usize,
+    /// Index of the layout that is the first layout group of the loaded keymap.
+    pub keymap_first_layout: usize,
+    /// IPC clients listening for events.
+    pub ipc_subscribers: Vec<UnixStream>,
 
     pub display_handle: DisplayHandle,
     pub compositor_state: CompositorState,
+    pub data_control_state: DataControlState,
     pub data_device_state: DataDeviceState,
+    /// Mime types offered by the current clipboard selection, empty when there is none.
+    pub clipboard_mime_types: Vec<String>,
+    pub layer_shell_state: WlrLayerShellState,
     pub output_manager_state: OutputManagerState,
+    pub pointer_constraints_state: PointerConstraintsState,
+    pub primary_selection_state: PrimarySelectionState,
+    /// Mime types offered by the current primary selection, empty when there is none.
+    pub primary_mime_types: Vec<String>,
+    pub relative_pointer_manager_state: RelativePointerManagerState,
     pub seat_state: SeatState<Self>,
     pub shm_state: ShmState,
+    pub xdg_decoration_state: XdgDecorationState,
     pub xdg_shell_state: XdgShellState,
+
+    pub xwayland: Option<XWayland>,
+    pub xwm: Option<X11Wm>,
+    /// X11 display number of the running Xwayland, exported as `DISPLAY` to spawned clients.
+    pub xdisplay: Option<u32>,
+    /// Command passed with `--command`, held back until Xwayland is ready so it gets `DISPLAY`.
+    pub startup_command: Option<String>,
 }
 
 impl NoWayState {
@@ -63,51 +135,96 @@ impl NoWayState {
         loop_handle: LoopHandle<'static, NoWayData>,
         loop_signal: LoopSignal,
         display: &mut Display<Self>,
+        config: Config,
+        socket_name: Option<&str>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let start_time = Instant::now();
 
-        let socket_name = Self::init_wayland_listener(&loop_handle, display)?;
+        let socket_name = Self::init_wayland_listener(&loop_handle, display, socket_name)?;
         let space = Space::default();
 
         let dh = display.handle();
         let compositor_state = CompositorState::new::<Self>(&dh);
+        let data_control_state = DataControlState::new(&dh);
         let data_device_state = DataDeviceState::new::<Self>(&dh);
+        let layer_shell_state = WlrLayerShellState::new::<Self>(&dh);
         let output_manager_state = OutputManagerState::new_with_xdg_output::<Self>(&dh);
+        let pointer_constraints_state = PointerConstraintsState::new(&dh);
+        let primary_selection_state = PrimarySelectionState::new::<Self>(&dh);
+        let relative_pointer_manager_state = RelativePointerManagerState::new::<Self>(&dh);
         let mut seat_state = SeatState::new();
         let shm_state = ShmState::new::<Self>(&dh, vec![]);
+        let xdg_decoration_state = XdgDecorationState::new::<Self>(&dh);
         let xdg_shell_state = XdgShellState::new::<Self>(&dh);
 
-        let mut seat = seat_state.new_wl_seat(&dh, "winit");
-        seat.add_keyboard(Default::default(), 200, 50).unwrap();
+        let mut seat = seat_state.new_wl_seat(&dh, config.seat_name.clone());
+        seat.add_keyboard(
+            xkb_config(&config.keyboard, &keymap_layouts(&config.keyboard, 0)),
+            config.keyboard.repeat_delay,
+            config.keyboard.repeat_rate,
+        )?;
         seat.add_pointer();
+        seat.add_touch();
 
         Ok(Self {
             start_time,
             loop_handle,
             loop_signal,
+            session: None,
+            config: config.clone(),
+            config_changed: false,
+            config_notice: None,
 
             socket_name,
             space,
+            pending_windows: Vec::new(),
+            workspaces: Workspaces::default(),
+            next_tiling_order: 0,
+            popups: PopupManager::default(),
 
             cursor_status: Arc::new(Mutex::new(CursorImageStatus::Default)),
-            pointer_location: (100.0, 100.0).into(),
+            pointer_location: config.initial_pointer_location,
+            absolute_pointer_location: None,
             seat,
+            touch_points: Vec::new(),
+            touch_grab: None,
+            suppressed_keys: Vec::new(),
+            keyboard_layout: 0,
+            keymap_first_layout: 0,
+            ipc_subscribers: Vec::new(),
 
             display_handle: dh,
             compositor_state,
+            data_control_state,
             data_device_state,
+            clipboard_mime_types: Vec::new(),
+            layer_shell_state,
             output_manager_state,
+            pointer_constraints_state,
+            primary_selection_state,
+            primary_mime_types: Vec::new(),
+            relative_pointer_manager_state,
             seat_state,
             shm_state,
+            xdg_decoration_state,
             xdg_shell_state,
+
+            xwayland: None,
+            xwm: None,
+            xdisplay: None,
+            startup_command: None,
         })
     }
 
     fn init_wayland_listener(
         handle: &LoopHandle<'static, NoWayData>,
         display: &mut Display<Self>,
+        socket_name: Option<&str>,
     ) -> Result<OsString, Box<dyn std::error::Error>> {
-        let listening_socket = ListeningSocketSource::new_auto().unwrap();
+        let listening_socket = match socket_name {
+            Some(socket_name) => ListeningSocketSource::with_name(socket_name)?,
+            None => ListeningSocketSource::new_auto()?,
+        };
         let socket_name = listening_socket.socket_name().to_os_string();
 
         handle.insert_source(listening_socket, move |client_stream, _, state| {
@@ -132,4 +249,38 @@ impl NoWayState {
 
         Ok(socket_name)
     }
+
+    /// Spawns the command passed with `--command`, if it did not run yet.
+    pub fn spawn_startup_command(&mut self) {
+        if let Some(command) = self.startup_command.take() {
+            self.spawn(&command);
+        }
+    }
+
+    /// Spawns a shell command as a client of this compositor.
+    pub fn spawn(&self, command: &str) {
+        tracing::info!("Spawning `{}`", command);
+        let mut child = Command::new("/bin/sh");
+        child
+            .arg("-c")
+            .arg(command)
+            .env("WAYLAND_DISPLAY", &self.socket_name);
+        if let Some(xdisplay) = self.xdisplay {
+            child.env("DISPLAY", format!(":{}", xdisplay));
+        }
+
+        // The shell runs in a second fork whose parent exits right away, so it is adopted by
+        // init and does not stay around as a zombie once it exits.
+        unsafe {
+            child.pre_exec(|| match fork()? {
+                ForkResult::Parent { .. } => libc::_exit(0),
+                ForkResult::Child => Ok(()),
+            });
+        }
+        let result = child.spawn().and_then(|mut child| child.wait());
+
+        if let Err(err) = result {
+            tracing::error!("Failed to spawn `{}`: {}", command, err);
+        }
+    }
 }
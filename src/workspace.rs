@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+
+use smithay::{
+    desktop::space::SpaceElement,
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::compositor::get_parent,
+};
+
+use crate::{
+    grabs::resize_grab::cancel_resize,
+    layout::{scrolling::Strip, LayoutKind},
+    render::window::WindowElement,
+    state::NoWayState,
+};
+
+/// Number of workspaces, numbered from 1.
+pub const WORKSPACE_COUNT: usize = 9;
+
+/// A window of a workspace that is not shown on any output.
+#[derive(Debug)]
+struct HiddenWindow {
+    window: WindowElement,
+    workspace: usize,
+    /// Location relative to the output the workspace was last shown on.
+    location: Point<i32, Logical>,
+    /// Minimized windows stay hidden when their workspace is shown.
+    minimized: bool,
+}
+
+/// Numbered workspaces. Every output shows one workspace, and a workspace is shown on at most
+/// one output. Windows of the shown workspaces live in the `Space`; windows of the other
+/// workspaces are kept here until their workspace is shown again.
+#[derive(Debug, Default)]
+pub struct Workspaces {
+    /// Workspace shown on each output, by output name.
+    active: HashMap<String, usize>,
+    /// Layouts chosen for workspaces, the others use the default from the config.
+    layouts: HashMap<usize, LayoutKind>,
+    /// Columns of the workspaces using the scrolling layout.
+    strips: HashMap<usize, Strip>,
+    hidden: Vec<HiddenWindow>,
+}
+
+impl Workspaces {
+    /// Workspace shown on the output. Outputs that did not show one yet get the lowest free
+    /// workspace.
+    pub fn active(&mut self, output: &Output) -> usize {
+        if let Some(workspace) = self.active.get(&output.name()) {
+            return *workspace;
+        }
+
+        let workspace = (1..=WORKSPACE_COUNT)
+            .find(|workspace| !self.active.values().any(|active| active == workspace))
+            .unwrap_or(WORKSPACE_COUNT);
+        self.active.insert(output.name(), workspace);
+        workspace
+    }
+
+    /// Forgets the workspace shown on a removed output, so it counts as hidden.
+    pub fn remove_output(&mut self, output: &Output) {
+        self.active.remove(&output.name());
+    }
+
+    pub fn layout(&self, workspace: usize) -> Option<LayoutKind> {
+        self.layouts.get(&workspace).copied()
+    }
+
+    pub fn set_layout(&mut self, workspace: usize, layout: LayoutKind) {
+        self.layouts.insert(workspace, layout);
+    }
+
+    /// Name of the output showing the workspace, if any.
+    pub fn output_of(&self, workspace: usize) -> Option<&str> {
+        self.active
+            .iter()
+            .find(|(_, active)| **active == workspace)
+            .map(|(output, _)| output.as_str())
+    }
+
+    /// Number of hidden windows on the workspace.
+    pub fn hidden_window_count(&self, workspace: usize) -> usize {
+        self.hidden
+            .iter()
+            .filter(|hidden| hidden.workspace == workspace)
+            .count()
+    }
+
+    /// Hidden windows of all workspaces.
+    pub fn hidden_windows(&self) -> impl Iterator<Item = &WindowElement> {
+        self.hidden.iter().map(|hidden| &hidden.window)
+    }
+
+    pub fn hidden_window_for_surface(&self, surface: &WlSurface) -> Option<&WindowElement> {
+        self.hidden
+            .iter()
+            .map(|hidden| &hidden.window)
+            .find(|window| window.wl_surface().as_ref() == Some(surface))
+    }
+
+    /// Forgets a hidden window, e.g. when it was destroyed.
+    pub fn remove_hidden(&mut self, window: &WindowElement) {
+        self.hidden.retain(|hidden| &hidden.window != window);
+        self.remove_from_strips(window);
+    }
+
+    pub fn strip(&mut self, workspace: usize) -> &mut Strip {
+        self.strips.entry(workspace).or_default()
+    }
+
+    pub fn remove_strip(&mut self, workspace: usize) {
+        self.strips.remove(&workspace);
+    }
+
+    /// Workspace whose strip holds the window.
+    pub fn strip_workspace(&self, window: &WindowElement) -> Option<usize> {
+        self.strips
+            .iter()
+            .find(|(_, strip)| strip.contains(window))
+            .map(|(workspace, _)| *workspace)
+    }
+
+    pub fn remove_from_strips(&mut self, window: &WindowElement) {
+        for strip in self.strips.values_mut() {
+            strip.remove(window);
+        }
+    }
+
+    /// Swaps two windows held by the same strip.
+    pub fn swap_in_strips(&mut self, a: &WindowElement, b: &WindowElement) {
+        for strip in self.strips.values_mut() {
+            strip.swap(a, b);
+        }
+    }
+}
+
+impl NoWayState {
+    /// Output the user is working on: the one under the pointer.
+    pub fn focused_output(&self) -> Option<Output> {
+        self.space
+            .output_under(self.pointer_location)
+            .next()
+            .or_else(|| self.space.outputs().next())
+            .cloned()
+    }
+
+    /// Output a mapped window belongs to: the one showing the strip holding it, otherwise the
+    /// one containing the center of its geometry. Scrolled windows may be outside their output.
+    pub fn output_for_window(&self, window: &WindowElement) -> Option<Output> {
+        let geometry = self.space.element_geometry(window)?;
+        let strip_output = self
+            .workspaces
+            .strip_workspace(window)
+            .and_then(|workspace| self.workspaces.output_of(workspace))
+            .and_then(|name| self.space.outputs().find(|output| output.name() == name));
+        if let Some(output) = strip_output {
+            return Some(output.clone());
+        }
+
+        let center = geometry.loc.to_f64() + geometry.size.to_f64().downscale(2.0).to_point();
+        self.space
+            .output_under(center)
+            .next()
+            .cloned()
+            .or_else(|| self.space.outputs_for_element(window).into_iter().next())
+    }
+
+    pub fn focused_window(&self) -> Option<WindowElement> {
+        let focus = self.seat.get_keyboard()?.current_focus()?;
+        self.window_for_surface(&focus)
+    }
+
+    /// Raises the window and gives it the keyboard focus.
+    pub fn focus_window(&mut self, window: &WindowElement) {
+        self.space.raise_element(window, true);
+        self.space.elements().for_each(|window| {
+            if let WindowElement::Xdg(window) = window {
+                window.toplevel().send_configure();
+            }
+        });
+        if let (WindowElement::X11(surface), Some(xwm)) = (window, &mut self.xwm) {
+            if let Err(err) = xwm.raise_window(surface) {
+                tracing::warn!("Failed to raise X11 window: {}", err);
+            }
+        }
+
+        let keyboard = self.seat.get_keyboard().unwrap();
+        keyboard.set_focus(self, window.wl_surface(), SERIAL_COUNTER.next_serial());
+    }
+
+    /// Unmaps the window and keeps it on the given workspace, which is currently hidden unless
+    /// the window is minimized.
+    fn hide_window(
+        &mut self,
+        window: WindowElement,
+        workspace: usize,
+        output: &Output,
+        minimized: bool,
+    ) {
+        let output_geo = self.space.output_geometry(output).unwrap();
+        let location = self.space.element_location(&window).unwrap() - output_geo.loc;
+
+        self.space.unmap_elem(&window);
+        self.cancel_window_grabs(&window);
+        self.workspaces.hidden.push(HiddenWindow {
+            window,
+            workspace,
+            location,
+            minimized,
+        });
+    }
+
+    /// Ends a pointer or touch grab on a window that is no longer mapped, so a move or resize
+    /// does not map it again.
+    fn cancel_window_grabs(&mut self, window: &WindowElement) {
+        let pointer = self.seat.get_pointer().unwrap();
+        let grab_surface = pointer
+            .grab_start_data()
+            .and_then(|start_data| start_data.focus)
+            .map(|(mut surface, _)| {
+                while let Some(parent) = get_parent(&surface) {
+                    surface = parent;
+                }
+                surface
+            });
+        if grab_surface.is_some() && grab_surface == window.wl_surface() {
+            let time = self.start_time.elapsed().as_millis() as u32;
+            pointer.unset_grab(self, SERIAL_COUNTER.next_serial(), time);
+            cancel_resize(window);
+        }
+
+        if self
+            .touch_grab
+            .as_ref()
+            .map_or(false, |grab| grab.window() == window)
+        {
+            self.touch_grab = None;
+            cancel_resize(window);
+        }
+    }
+
+    /// Gives the keyboard focus to the topmost window on the output, if the focused window is
+    /// no longer mapped.
+    fn refresh_focus(&mut self, output: &Output) {
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let focus_mapped = keyboard
+            .current_focus()
+            .map_or(false, |focus| self.window_for_surface(&focus).is_some());
+        if focus_mapped || self.exclusive_layer_surface().is_some() {
+            return;
+        }
+
+        let window = self
+            .space
+            .elements()
+            .rev()
+            .find(|window| self.output_for_window(window).as_ref() == Some(output))
+            .cloned();
+        if let Some(window) = window.as_ref() {
+            self.space.raise_element(window, true);
+        }
+        keyboard.set_focus(
+            self,
+            window.and_then(|window| window.wl_surface()),
+            SERIAL_COUNTER.next_serial(),
+        );
+    }
+
+    /// Shows the workspace on the output. A workspace already shown on another output stays
+    /// there, in which case `false` is returned.
+    pub fn switch_workspace(&mut self, output: &Output, workspace: usize) -> bool {
+        let current = self.workspaces.active(output);
+        if current == workspace {
+            return true;
+        }
+        if let Some(other) = self.workspaces.output_of(workspace) {
+            tracing::info!("Workspace {} is already shown on {}", workspace, other);
+            return false;
+        }
+
+        let windows = self
+            .space
+            .elements()
+            .filter(|window| self.output_for_window(window).as_ref() == Some(output))
+            .cloned()
+            .collect::<Vec<_>>();
+        for window in windows {
+            self.hide_window(window, current, output, false);
+        }
+
+        self.show_hidden_windows(output, workspace);
+        self.workspaces.active.insert(output.name(), workspace);
+        tracing::info!("Switched {} to workspace {}", output.name(), workspace);
+        self.arrange_output(output);
+        self.refresh_focus(output);
+        true
+    }
+
+    /// Maps the windows of the workspace, which is about to be shown on the output, except for
+    /// the minimized ones.
+    fn show_hidden_windows(&mut self, output: &Output, workspace: usize) {
+        let output_geo = self.space.output_geometry(output).unwrap();
+        let (shown, hidden) = std::mem::take(&mut self.workspaces.hidden)
+            .into_iter()
+            .partition::<Vec<_>, _>(|hidden| hidden.workspace == workspace && !hidden.minimized);
+        self.workspaces.hidden = hidden;
+        for hidden in shown {
+            self.space
+                .map_element(hidden.window, output_geo.loc + hidden.location, false);
+        }
+    }
+
+    /// Shows the workspace a new output gets, with the windows it kept while it was hidden,
+    /// e.g. because every output was unplugged.
+    pub fn add_output(&mut self, output: &Output) {
+        let workspace = self.workspaces.active(output);
+        self.show_hidden_windows(output, workspace);
+        self.arrange_output(output);
+        self.refresh_focus(output);
+    }
+
+    /// Unmaps an output that is gone. Its windows move to another output, keeping their
+    /// position relative to the output, or stay on its workspace if it was the last one.
+    pub fn remove_output(&mut self, output: &Output) {
+        let target = self.space.outputs().find(|other| *other != output).cloned();
+        let workspace = self.workspaces.active(output);
+        if target.is_some() {
+            // Columns of a scrolling workspace outside the view move along.
+            self.unpark_strip(output, workspace);
+        }
+
+        let windows = self
+            .space
+            .elements()
+            .filter(|window| self.output_for_window(window).as_ref() == Some(output))
+            .cloned()
+            .collect::<Vec<_>>();
+        match &target {
+            Some(target) => {
+                let from_geo = self.space.output_geometry(output).unwrap();
+                let to_geo = self.space.output_geometry(target).unwrap();
+                for window in windows {
+                    self.workspaces.remove_from_strips(&window);
+                    let location =
+                        self.space.element_location(&window).unwrap() - from_geo.loc + to_geo.loc;
+                    self.space.map_element(window, location, false);
+                }
+            }
+            None => {
+                for window in windows {
+                    self.hide_window(window, workspace, output, false);
+                }
+            }
+        }
+
+        self.space.unmap_output(output);
+        self.workspaces.remove_output(output);
+        if let Some(target) = target {
+            self.arrange_output(&target);
+            self.refresh_focus(&target);
+        }
+    }
+
+    /// Moves a mapped window to the workspace, keeping its position relative to the output.
+    pub fn move_window_to_workspace(&mut self, window: &WindowElement, workspace: usize) {
+        let Some(from) = self.output_for_window(window) else {
+            return;
+        };
+        if self.workspaces.active(&from) == workspace {
+            return;
+        }
+        self.workspaces.remove_from_strips(window);
+
+        let target = self
+            .workspaces
+            .output_of(workspace)
+            .and_then(|name| self.space.outputs().find(|output| output.name() == name))
+            .cloned();
+        match target {
+            Some(to) => {
+                let from_geo = self.space.output_geometry(&from).unwrap();
+                let to_geo = self.space.output_geometry(&to).unwrap();
+                let location =
+                    self.space.element_location(window).unwrap() - from_geo.loc + to_geo.loc;
+                self.space.map_element(window.clone(), location, false);
+                self.arrange_output(&to);
+            }
+            None => self.hide_window(window.clone(), workspace, &from, false),
+        }
+
+        self.arrange_output(&from);
+        self.refresh_focus(&from);
+    }
+
+    /// Hides the window until it is restored with [`Self::unminimize_window`].
+    pub fn minimize_window(&mut self, window: &WindowElement) {
+        let Some(output) = self.output_for_window(window) else {
+            return;
+        };
+        let workspace = self.workspaces.active(&output);
+
+        self.hide_window(window.clone(), workspace, &output, true);
+        self.arrange_output(&output);
+        self.refresh_focus(&output);
+    }
+
+    /// Shows the most recently minimized window of the workspace on the output again and
+    /// focuses it. Returns whether there was one.
+    pub fn unminimize_window(&mut self, output: &Output) -> bool {
+        let workspace = self.workspaces.active(output);
+        let Some(index) = self
+            .workspaces
+            .hidden
+            .iter()
+            .rposition(|hidden| hidden.workspace == workspace && hidden.minimized)
+        else {
+            return false;
+        };
+
+        let hidden = self.workspaces.hidden.remove(index);
+        let output_geo = self.space.output_geometry(output).unwrap();
+        self.space.map_element(
+            hidden.window.clone(),
+            output_geo.loc + hidden.location,
+            true,
+        );
+        self.arrange_output(output);
+        self.focus_window(&hidden.window);
+        true
+    }
+
+    pub fn workspace_layout(&self, workspace: usize) -> LayoutKind {
+        self.workspaces
+            .layout(workspace)
+            .unwrap_or(self.config.layout.default)
+    }
+
+    /// Lines describing every workspace, for the IPC `workspaces` query.
+    pub fn describe_workspaces(&mut self) -> Vec<String> {
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        for output in &outputs {
+            self.workspaces.active(output);
+        }
+
+        (1..=WORKSPACE_COUNT)
+            .map(|workspace| {
+                // A workspace of an output that is gone counts as hidden.
+                let output = self
+                    .workspaces
+                    .output_of(workspace)
+                    .and_then(|name| outputs.iter().find(|output| output.name() == name));
+                let Some(output) = output else {
+                    return format!(
+                        "{} hidden with {} windows, {} layout",
+                        workspace,
+                        self.workspaces.hidden_window_count(workspace),
+                        self.workspace_layout(workspace).name()
+                    );
+                };
+
+                let windows = self
+                    .space
+                    .elements()
+                    .filter(|window| self.output_for_window(window).as_ref() == Some(output))
+                    .count();
+                format!(
+                    "{} shown on {} with {} windows, {} layout",
+                    workspace,
+                    output.name(),
+                    windows,
+                    self.workspace_layout(workspace).name()
+                )
+            })
+            .collect()
+    }
+}